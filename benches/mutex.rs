@@ -0,0 +1,95 @@
+//! Compare the two flavors of the async Mutex, on a single thread.
+//!
+//! Run with `cargo bench --features mutex --bench mutex`.
+
+use std::{
+    future::{Future, poll_fn},
+    hint::black_box,
+    pin::pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use futures::{executor::block_on, future::join_all, task::noop_waker_ref};
+use synchrony::{sync, unsync};
+
+const ITERS: u32 = 1_000_000;
+const TASKS: u32 = 8;
+
+// Yield to the executor once, so that the other tasks queue up on the lock.
+fn yield_now() -> impl Future<Output = ()> {
+    let mut yielded = false;
+    poll_fn(move |cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+}
+
+fn bench(name: &str, iters: u32, f: impl FnOnce()) {
+    let start = Instant::now();
+    f();
+    let nanos = start.elapsed().as_secs_f64() * 1e9 / f64::from(iters);
+    println!("{name:<24} {nanos:>8.2} ns/iter");
+}
+
+macro_rules! bench_flavor {
+    ($sync:ident) => {{
+        let flavor = stringify!($sync);
+
+        let mutex = $sync::mutex::Mutex::new(0u64);
+        bench(&format!("{flavor}/try_lock"), ITERS, || {
+            for _ in 0..ITERS {
+                *black_box(&mutex).try_lock().unwrap() += 1;
+            }
+        });
+
+        let mutex = $sync::mutex::Mutex::new(0u64);
+        bench(&format!("{flavor}/lock"), ITERS, || {
+            block_on(async {
+                for _ in 0..ITERS {
+                    *black_box(&mutex).lock().await += 1;
+                }
+            })
+        });
+
+        // Hand the lock over to a queued waiter, without an executor.
+        let mutex = $sync::mutex::Mutex::new(0u64);
+        bench(&format!("{flavor}/handoff"), ITERS, || {
+            let mut cx = Context::from_waker(noop_waker_ref());
+            let mut guard = mutex.try_lock().unwrap();
+            for _ in 0..ITERS {
+                let mut next = pin!(black_box(&mutex).lock());
+                assert!(next.as_mut().poll(&mut cx).is_pending());
+                drop(guard);
+                guard = match next.poll(&mut cx) {
+                    Poll::Ready(guard) => guard,
+                    Poll::Pending => unreachable!("the lock was released"),
+                };
+                *guard += 1;
+            }
+        });
+
+        let mutex = $sync::mutex::Mutex::new(0u64);
+        let per_task = ITERS / TASKS / 10;
+        bench(&format!("{flavor}/contended"), per_task * TASKS, || {
+            block_on(join_all((0..TASKS).map(|_| async {
+                for _ in 0..per_task {
+                    let mut guard = mutex.lock().await;
+                    yield_now().await;
+                    *guard += 1;
+                }
+            })));
+        });
+        assert_eq!(*mutex.try_lock().unwrap(), (per_task * TASKS) as u64);
+    }};
+}
+
+fn main() {
+    bench_flavor!(sync);
+    bench_flavor!(unsync);
+}
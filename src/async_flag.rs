@@ -4,6 +4,34 @@ pub mod sync {
 
     impl crate::AssertMt for AsyncFlag {}
     impl crate::AssertMt for AsyncFlagHandle {}
+
+    #[cfg(feature = "std")]
+    impl AsyncFlag {
+        /// Wait for [`AsyncFlagHandle::notify`] to be called from synchronous
+        /// code, parking the thread meanwhile.
+        ///
+        /// # Async contexts
+        ///
+        /// Calling it from an async task blocks the executor thread, and
+        /// deadlocks if the flag is to be notified by a task of that thread.
+        /// This can't be detected: use [`wait`](Self::wait) there instead.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use std::thread;
+        ///
+        /// use synchrony::sync::async_flag::AsyncFlag;
+        ///
+        /// let flag = AsyncFlag::new();
+        /// let handle = flag.handle();
+        /// thread::spawn(move || handle.notify());
+        /// flag.wait_blocking();
+        /// ```
+        pub fn wait_blocking(self) {
+            crate::blocking::block_on(self.wait())
+        }
+    }
 }
 
 /// Singlethreaded notifier
@@ -13,12 +41,12 @@ pub mod unsync {
 
 macro_rules! impl_notify {
     ($sync:ident) => {
-        use std::{
+        use core::{
             pin::Pin,
             task::{Context, Poll},
         };
 
-        use crate::$sync::{flag::Flag, shared::Shared, waker_slot::WakerSlot};
+        use crate::$sync::{flag::Flag, once::OnceLock, shared::Shared, waker_slot::WakerSlot};
 
         #[derive(Debug)]
         struct Inner {
@@ -72,7 +100,8 @@ macro_rules! impl_notify {
         /// successfully.
         #[derive(Debug)]
         pub struct AsyncFlag {
-            flag: AsyncFlagImpl,
+            // Allocated with the first handle, so that `new` can be `const`.
+            flag: OnceLock<AsyncFlagImpl>,
         }
 
         impl Default for AsyncFlag {
@@ -83,25 +112,40 @@ macro_rules! impl_notify {
 
         impl AsyncFlag {
             /// Create [`AsyncFlag`].
+            #[cfg(not(loom))]
+            pub const fn new() -> Self {
+                Self {
+                    flag: OnceLock::new(),
+                }
+            }
+
+            /// Create [`AsyncFlag`].
+            ///
+            /// This `new` is not `const` due to loom not supporting it.
+            #[cfg(loom)]
             pub fn new() -> Self {
                 Self {
-                    flag: AsyncFlagImpl::new(),
+                    flag: OnceLock::new(),
                 }
             }
 
             /// Get a handle to notify the flag.
             pub fn handle(&self) -> AsyncFlagHandle {
-                AsyncFlagHandle::new(self.flag.clone())
+                AsyncFlagHandle::new(self.flag.get_or_init(AsyncFlagImpl::new).clone())
             }
 
             /// Returns whether the event has been notified.
             pub fn notified(&self) -> bool {
-                self.flag.notified()
+                self.flag.get().is_some_and(AsyncFlagImpl::notified)
             }
 
             /// Wait for [`AsyncFlagHandle::notify`] to be called.
             pub async fn wait(self) {
-                self.flag.await
+                // Without a handle, this never completes.
+                self.flag
+                    .into_inner()
+                    .unwrap_or_else(AsyncFlagImpl::new)
+                    .await
             }
         }
 
@@ -1,14 +1,33 @@
 //!  Atomic scalars
 
 /// Multithreaded atomic scalars
+///
+/// With the `portable-atomic` feature enabled, these are provided by the
+/// [`portable-atomic`](https://docs.rs/portable-atomic) crate instead of
+/// [`core::sync::atomic`], which makes 64-bit atomics available on more targets
+/// and adds `AtomicI128` and `AtomicU128`.
 pub mod sync {
+    #[cfg(not(feature = "portable-atomic"))]
     crate::cfg_loom! {
-        pub use std::sync::atomic::{
+        pub use core::sync::atomic::{
             AtomicBool, AtomicI8, AtomicI16, AtomicI32, AtomicI64, AtomicIsize, AtomicU8, AtomicU16,
             AtomicU32, AtomicU64, AtomicUsize,
         };
     }
+
+    #[cfg(feature = "portable-atomic")]
+    crate::cfg_loom! {
+        pub use portable_atomic::{
+            AtomicBool, AtomicI8, AtomicI16, AtomicI32, AtomicI64, AtomicIsize, AtomicU8, AtomicU16,
+            AtomicU32, AtomicU64, AtomicUsize,
+        };
+    }
+
+    // loom doesn't model 128-bit atomics, so these are always the
+    // `portable-atomic` ones.
+    #[cfg(feature = "portable-atomic")]
+    pub use portable_atomic::{AtomicI128, AtomicU128};
 }
 
-/// Singlethreaded atomic scalars based on [`std::cell::Cell`]
+/// Singlethreaded atomic scalars based on [`core::cell::Cell`]
 pub mod unsync;
@@ -1,7 +1,7 @@
-use std::{fmt::Debug, sync::atomic::Ordering};
+use core::{fmt::Debug, sync::atomic::Ordering};
 
 crate::cfg_loom! {
-    use std::cell::Cell;
+    use core::cell::Cell;
 }
 
 atomic_int!(AtomicU8(u8));
@@ -14,13 +14,17 @@ atomic_int!(AtomicI16(i16));
 atomic_int!(AtomicI32(i32));
 atomic_int!(AtomicI64(i64));
 atomic_int!(AtomicIsize(isize));
+#[cfg(feature = "portable-atomic")]
+atomic_int!(AtomicU128(u128), "crate::sync::atomic::AtomicU128");
+#[cfg(feature = "portable-atomic")]
+atomic_int!(AtomicI128(i128), "crate::sync::atomic::AtomicI128");
 
-/// A singlethreaded [`AtomicBool`] based on [`Cell`](std::cell::Cell)
+/// A singlethreaded [`AtomicBool`] based on [`Cell`](core::cell::Cell)
 ///
 /// All [`Ordering`] passed into the functions are ignored since no actual
 /// atomicity is needed.
 ///
-/// [`AtomicBool`]: std::sync::atomic::AtomicBool
+/// [`AtomicBool`]: core::sync::atomic::AtomicBool
 pub struct AtomicBool {
     v: Cell<bool>,
 }
@@ -38,11 +42,25 @@ impl Default for AtomicBool {
 }
 
 impl Debug for AtomicBool {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         Debug::fmt(&self.v.get(), f)
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for AtomicBool {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.v.get().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for AtomicBool {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        bool::deserialize(deserializer).map(Self::new)
+    }
+}
+
 impl AtomicBool {
     /// Creates a new [`AtomicBool`]
     #[cfg(not(loom))]
@@ -195,10 +213,13 @@ impl AtomicBool {
 
 macro_rules! atomic_int {
     ($t:ident($i:ty)) => {
-        #[doc = concat!("A singlethreaded [`", stringify!($t), "`] based on [`Cell`](std::cell::Cell)\n\n")]
+        atomic_int!($t($i), concat!("core::sync::atomic::", stringify!($t)));
+    };
+    ($t:ident($i:ty), $link:expr) => {
+        #[doc = concat!("A singlethreaded [`", stringify!($t), "`] based on [`Cell`](core::cell::Cell)\n\n")]
         /// All [`Ordering`] passed into the functions are ignored since no actual
         /// atomicity is needed.
-        #[doc = concat!("\n\n[`", stringify!($t), "`]: std::sync::atomic::", stringify!($t))]
+        #[doc = concat!("\n\n[`", stringify!($t), "`]: ", $link)]
         #[repr(transparent)]
         pub struct $t {
             v: Cell<$i>,
@@ -217,11 +238,25 @@ macro_rules! atomic_int {
         }
 
         impl Debug for $t {
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
                 Debug::fmt(&self.v.get(), f)
             }
         }
 
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $t {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.v.get().serialize(serializer)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $t {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                <$i>::deserialize(deserializer).map(Self::new)
+            }
+        }
+
         impl $t {
             #[cfg(not(loom))]
             #[doc = concat!("Creates a new [`", stringify!($t), "`]")]
@@ -8,6 +8,36 @@ pub mod sync {
     impl<T: Send> crate::AssertMt for BiLock<T> {}
     impl<T: Send> crate::AssertMt for BiLockAcquire<'_, T> {}
     impl<T: Send> crate::AssertMt for BiLockGuard<'_, T> {}
+
+    #[cfg(feature = "std")]
+    impl<T> BiLock<T> {
+        /// Acquire the lock from synchronous code, parking the thread until it
+        /// is acquired.
+        ///
+        /// # Async contexts
+        ///
+        /// Calling it from an async task blocks the executor thread, and
+        /// deadlocks if the other half is held by a task of that thread. This
+        /// can't be detected: use [`lock`](Self::lock) there instead.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use std::thread;
+        ///
+        /// use synchrony::sync::bilock::BiLock;
+        ///
+        /// let (a, b) = BiLock::new(0);
+        /// thread::spawn(move || *b.blocking_lock() += 1)
+        ///     .join()
+        ///     .unwrap();
+        /// assert_eq!(*a.blocking_lock(), 1);
+        /// ```
+        #[track_caller]
+        pub fn blocking_lock(&self) -> BiLockGuard<'_, T> {
+            crate::blocking::block_on(self.lock())
+        }
+    }
 }
 
 /// Singlethreaded BiLock
@@ -17,7 +47,7 @@ pub mod unsync {
 
 macro_rules! impl_bilock {
     ($sync:ident) => {
-        use std::{
+        use core::{
             fmt::Debug,
             future::Future,
             ops::{Deref, DerefMut},
@@ -26,10 +56,14 @@ macro_rules! impl_bilock {
         };
 
         crate::cfg_loom! {
-            use std::cell::UnsafeCell;
+            use core::cell::UnsafeCell;
         }
 
-        use crate::$sync::{flag::Flag, shared::Shared, waker_slot::WakerSlot};
+        use crate::{
+            deadlock::{LockId, Owner, Site},
+            metrics::{Metrics, WaitStart},
+            $sync::{flag::Flag, shared::Shared, waker_slot::WakerSlot},
+        };
 
         /// A lock shared by two parties.
         pub struct BiLock<T>(Shared<Inner<T>>);
@@ -38,7 +72,7 @@ macro_rules! impl_bilock {
         where
             T: Debug,
         {
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
                 f.debug_struct("BiLock")
                     .field("locked", &self.0.locked.get())
                     .finish()
@@ -53,13 +87,27 @@ macro_rules! impl_bilock {
                     data: UnsafeCell::new(data),
                     waiter: WakerSlot::new(),
                     locked: Flag::new(false),
+                    id: LockId::new(),
+                    metrics: Metrics::new(),
                 });
                 (Self(inner.clone()), Self(inner))
             }
 
             /// Acquires the lock, returning a future that resolves to a guard
+            #[track_caller]
             pub fn lock(&self) -> BiLockAcquire<'_, T> {
-                BiLockAcquire { inner: &self.0 }
+                BiLockAcquire {
+                    inner: &self.0,
+                    site: Site::caller(),
+                    wait: WaitStart::new(),
+                }
+            }
+
+            /// Get a snapshot of the contention counters of this lock, shared by
+            /// both halves.
+            #[cfg(feature = "metrics")]
+            pub fn stats(&self) -> crate::metrics::LockStats {
+                self.0.metrics.stats()
             }
 
             /// Attempts to join two `BiLock`s into their original data.
@@ -96,6 +144,8 @@ macro_rules! impl_bilock {
         /// Future for acquiring a [`BiLock`]
         pub struct BiLockAcquire<'a, T> {
             inner: &'a Inner<T>,
+            site: Site,
+            wait: WaitStart,
         }
 
         impl<'a, T> Future for BiLockAcquire<'a, T> {
@@ -103,18 +153,32 @@ macro_rules! impl_bilock {
 
             fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
                 let this = self.get_mut();
+                let owner = Owner::thread();
+                this.inner.id.wait(owner, this.site);
                 if this.inner.locked.swap(true) {
+                    this.inner.metrics.wait(&mut this.wait);
                     this.inner.waiter.register(cx.waker());
                     Poll::Pending
                 } else {
+                    this.inner.id.acquire(owner, this.site);
+                    this.inner.metrics.acquire(&mut this.wait);
                     Poll::Ready(BiLockGuard { inner: this.inner })
                 }
             }
         }
 
+        #[cfg(feature = "metrics")]
+        impl<T> Drop for BiLockAcquire<'_, T> {
+            fn drop(&mut self) {
+                self.inner.metrics.cancel(&mut self.wait);
+            }
+        }
+
         struct Inner<T: ?Sized> {
             locked: Flag,
             waiter: WakerSlot,
+            id: LockId,
+            metrics: Metrics,
             data: UnsafeCell<T>,
         }
 
@@ -139,6 +203,7 @@ macro_rules! impl_bilock {
 
         impl<T: ?Sized> Drop for BiLockGuard<'_, T> {
             fn drop(&mut self) {
+                self.inner.id.release();
                 self.inner.locked.swap(false);
                 self.inner.waiter.wake();
             }
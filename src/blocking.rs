@@ -0,0 +1,51 @@
+//! Bridges from synchronous code into async primitives, by parking the thread
+//! until the future is woken.
+
+use alloc::{sync::Arc, task::Wake};
+use core::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+crate::cfg_loom! {
+    use std::thread::{Thread, current, park};
+}
+crate::cfg_loom! {
+    use std::thread_local;
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+thread_local! {
+    // One waker per thread, reused across calls.
+    static WAKER: Waker = Waker::from(Arc::new(ThreadWaker(current())));
+}
+
+/// Run `fut` to completion on the current thread, parking it while the future
+/// is pending.
+///
+/// Whether the thread is running an async task can't be detected, in which
+/// case this blocks its executor.
+pub(crate) fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    WAKER.with(|waker| {
+        let mut cx = Context::from_waker(waker);
+        loop {
+            if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+                return output;
+            }
+            park();
+        }
+    })
+}
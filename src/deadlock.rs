@@ -0,0 +1,357 @@
+//! Lock-order deadlock detection.
+//!
+//! With the `deadlock-detection` feature enabled, every lock records which
+//! other locks its owner already held when it was acquired. These "held A
+//! while acquiring B" edges form a global lock-order graph, and the first time
+//! an edge closes a cycle in that graph, the cycle is reported through the hook
+//! installed with [`set_hook`] (printing to stderr by default). This catches
+//! inconsistent lock ordering before it actually deadlocks. Only the locks
+//! taken by users are recorded, not the internal ones of other primitives.
+//!
+//! Every acquisition, blocking or async, is attributed to the thread it
+//! happens on, since tasks have no identity that `try_lock` could see. This
+//! has limits for async code:
+//!
+//! - Tasks sharing a thread are seen as one owner. A task locking `B` while
+//!   another task on the same thread holds `A` across an `.await` records an
+//!   `A -> B` edge, which may be reported as part of a cycle that can't
+//!   deadlock.
+//! - A task that moves to another thread while holding a lock, as on a
+//!   work-stealing executor, records no edges from it on the new thread.
+//!
+//! Without the feature, all the bookkeeping compiles down to nothing.
+
+pub(crate) use imp::{Held, LockId, Owner, Site};
+#[cfg(feature = "deadlock-detection")]
+pub use imp::{LockOrderCycle, LockOrderEdge, set_hook};
+
+#[cfg(feature = "deadlock-detection")]
+mod imp {
+    use core::sync::atomic::Ordering;
+    use std::{
+        boxed::Box,
+        collections::BTreeMap,
+        eprintln, fmt,
+        panic::Location,
+        sync::{Mutex, PoisonError, RwLock, atomic::AtomicUsize},
+        thread::{self, ThreadId},
+        vec::Vec,
+    };
+
+    type Hook = Box<dyn Fn(&LockOrderCycle) + Send + Sync>;
+
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+    static GRAPH: Mutex<Graph> = Mutex::new(Graph::new());
+    static HOOK: RwLock<Option<Hook>> = RwLock::new(None);
+
+    /// Replace the function called when a lock-order cycle is found.
+    ///
+    /// The default hook prints the cycle to stderr.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::{
+    ///     Arc,
+    ///     atomic::{AtomicUsize, Ordering},
+    /// };
+    ///
+    /// use synchrony::{deadlock, sync::mutex_blocking::Mutex};
+    ///
+    /// let found = Arc::new(AtomicUsize::new(0));
+    /// let counter = found.clone();
+    /// deadlock::set_hook(move |cycle| {
+    ///     assert_eq!(cycle.edges().len(), 2);
+    ///     counter.fetch_add(1, Ordering::Relaxed);
+    /// });
+    ///
+    /// let a = Mutex::new(());
+    /// let b = Mutex::new(());
+    /// {
+    ///     let _a = a.lock();
+    ///     let _b = b.lock();
+    /// }
+    /// {
+    ///     // Never deadlocks here, but the order is inconsistent with the above.
+    ///     let _b = b.lock();
+    ///     let _a = a.lock();
+    /// }
+    /// assert_eq!(found.load(Ordering::Relaxed), 1);
+    /// ```
+    pub fn set_hook(hook: impl Fn(&LockOrderCycle) + Send + Sync + 'static) {
+        *HOOK.write().unwrap_or_else(PoisonError::into_inner) = Some(Box::new(hook));
+    }
+
+    /// One edge of the lock-order graph: a lock was acquired at
+    /// [`acquired`](Self::acquired) while another one, acquired at
+    /// [`held`](Self::held), was still held by the same owner.
+    #[derive(Debug, Clone, Copy)]
+    pub struct LockOrderEdge {
+        held: &'static Location<'static>,
+        acquired: &'static Location<'static>,
+    }
+
+    impl LockOrderEdge {
+        /// Where the already-held lock was acquired.
+        pub fn held(&self) -> &'static Location<'static> {
+            self.held
+        }
+
+        /// Where the second lock was acquired.
+        pub fn acquired(&self) -> &'static Location<'static> {
+            self.acquired
+        }
+    }
+
+    /// A cycle in the lock-order graph, i.e. a potential deadlock.
+    #[derive(Debug, Clone)]
+    pub struct LockOrderCycle {
+        edges: Vec<LockOrderEdge>,
+    }
+
+    impl LockOrderCycle {
+        /// The edges forming the cycle. The last one is the edge that has just
+        /// been recorded and closed the cycle.
+        pub fn edges(&self) -> &[LockOrderEdge] {
+            &self.edges
+        }
+    }
+
+    impl fmt::Display for LockOrderCycle {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            writeln!(f, "lock-order cycle detected:")?;
+            for edge in &self.edges {
+                writeln!(
+                    f,
+                    "  lock acquired at {} while holding lock acquired at {}",
+                    edge.acquired, edge.held
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) struct Owner(ThreadId);
+
+    impl Owner {
+        pub fn thread() -> Self {
+            Self(thread::current().id())
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) struct Site(&'static Location<'static>);
+
+    impl Site {
+        #[track_caller]
+        pub fn caller() -> Self {
+            Self(Location::caller())
+        }
+    }
+
+    struct Graph {
+        // `edges[a][b]` is set when `b` was acquired while holding `a`.
+        edges: BTreeMap<usize, BTreeMap<usize, LockOrderEdge>>,
+        holders: BTreeMap<usize, (Owner, Site)>,
+    }
+
+    impl Graph {
+        const fn new() -> Self {
+            Self {
+                edges: BTreeMap::new(),
+                holders: BTreeMap::new(),
+            }
+        }
+
+        // Depth-first search for a path `from -> ... -> to`, returning its edges.
+        fn path(&self, from: usize, to: usize) -> Option<Vec<LockOrderEdge>> {
+            let mut visited = Vec::new();
+            let mut stack = Vec::from([(from, Vec::new())]);
+            while let Some((node, path)) = stack.pop() {
+                if node == to {
+                    return Some(path);
+                }
+                if visited.contains(&node) {
+                    continue;
+                }
+                visited.push(node);
+                for (&next, edge) in self.edges.get(&node).into_iter().flatten() {
+                    let mut path = path.clone();
+                    path.push(*edge);
+                    stack.push((next, path));
+                }
+            }
+            None
+        }
+    }
+
+    /// Identity of a lock in the lock-order graph, assigned on first use.
+    #[derive(Debug, Default)]
+    pub(crate) struct LockId(AtomicUsize);
+
+    impl LockId {
+        pub const fn new() -> Self {
+            Self(AtomicUsize::new(0))
+        }
+
+        fn get(&self) -> usize {
+            let id = self.0.load(Ordering::Relaxed);
+            if id != 0 {
+                return id;
+            }
+            let new = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            match self
+                .0
+                .compare_exchange(0, new, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => new,
+                Err(id) => id,
+            }
+        }
+
+        /// Record that `owner` is trying to acquire this lock at `site`.
+        pub fn wait(&self, owner: Owner, site: Site) {
+            let id = self.get();
+            let mut cycles = Vec::new();
+            {
+                let mut graph = GRAPH.lock().unwrap_or_else(PoisonError::into_inner);
+                let held = graph
+                    .holders
+                    .iter()
+                    .filter(|(held, (o, _))| **held != id && *o == owner)
+                    .map(|(held, (_, held_site))| (*held, *held_site))
+                    .collect::<Vec<_>>();
+                for (held, held_site) in held {
+                    let edge = LockOrderEdge {
+                        held: held_site.0,
+                        acquired: site.0,
+                    };
+                    let out = graph.edges.entry(held).or_default();
+                    if out.contains_key(&id) {
+                        continue;
+                    }
+                    out.insert(id, edge);
+                    if let Some(mut edges) = graph.path(id, held) {
+                        edges.push(edge);
+                        cycles.push(LockOrderCycle { edges });
+                    }
+                }
+            }
+            for cycle in cycles {
+                report(&cycle);
+            }
+        }
+
+        /// Record that `owner` has acquired this lock at `site`.
+        pub fn acquire(&self, owner: Owner, site: Site) {
+            let id = self.get();
+            GRAPH
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .holders
+                .insert(id, (owner, site));
+        }
+
+        /// Record that `owner` has acquired this lock at `site`, and release it
+        /// when the returned [`Held`] is dropped.
+        pub fn hold(&self, owner: Owner, site: Site) -> Held<'_> {
+            self.acquire(owner, site);
+            Held(self)
+        }
+
+        /// Record that this lock has been released.
+        pub fn release(&self) {
+            let id = self.0.load(Ordering::Relaxed);
+            if id != 0 {
+                GRAPH
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .holders
+                    .remove(&id);
+            }
+        }
+    }
+
+    /// Releases a [`LockId`] on drop.
+    #[derive(Debug)]
+    pub(crate) struct Held<'a>(&'a LockId);
+
+    impl Drop for Held<'_> {
+        fn drop(&mut self) {
+            self.0.release()
+        }
+    }
+
+    impl Drop for LockId {
+        fn drop(&mut self) {
+            let id = *self.0.get_mut();
+            if id != 0 {
+                let mut graph = GRAPH.lock().unwrap_or_else(PoisonError::into_inner);
+                graph.holders.remove(&id);
+                graph.edges.remove(&id);
+                for out in graph.edges.values_mut() {
+                    out.remove(&id);
+                }
+            }
+        }
+    }
+
+    fn report(cycle: &LockOrderCycle) {
+        match &*HOOK.read().unwrap_or_else(PoisonError::into_inner) {
+            Some(hook) => hook(cycle),
+            None => eprintln!("{cycle}"),
+        }
+    }
+}
+
+#[cfg(not(feature = "deadlock-detection"))]
+mod imp {
+    use core::marker::PhantomData;
+
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) struct Owner;
+
+    impl Owner {
+        #[inline(always)]
+        pub fn thread() -> Self {
+            Self
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) struct Site;
+
+    impl Site {
+        #[inline(always)]
+        pub fn caller() -> Self {
+            Self
+        }
+    }
+
+    #[derive(Debug, Default)]
+    pub(crate) struct LockId;
+
+    impl LockId {
+        pub const fn new() -> Self {
+            Self
+        }
+
+        #[inline(always)]
+        pub fn wait(&self, _: Owner, _: Site) {}
+
+        #[inline(always)]
+        pub fn acquire(&self, _: Owner, _: Site) {}
+
+        #[inline(always)]
+        pub fn hold(&self, _: Owner, _: Site) -> Held<'_> {
+            Held(PhantomData)
+        }
+
+        #[inline(always)]
+        pub fn release(&self) {}
+    }
+
+    #[derive(Debug)]
+    pub(crate) struct Held<'a>(PhantomData<&'a ()>);
+}
@@ -1,9 +1,219 @@
-/// Notify async tasks or threads.
+//! Notify async tasks or threads.
+//!
+//! Both flavors have the same API: the `sync` one wraps [`event_listener`],
+//! and the `unsync` one wraps [`local_event`].
+
+/// Multithreaded [`Event`](sync::Event) based on [`event_listener`]
 pub mod sync {
-    pub use event_listener::{Event, EventListener};
+    use event_listener::{Event as RawEvent, EventListener as RawListener};
+
+    super::impl_event!(sync);
 
     impl crate::AssertMt for Event {}
     impl crate::AssertMt for EventListener {}
 }
-#[doc(inline)]
-pub use local_event as unsync;
+
+/// Singlethreaded [`Event`](unsync::Event) based on [`local_event`]
+pub mod unsync {
+    use local_event::{Event as RawEvent, EventListener as RawListener};
+
+    super::impl_event!(unsync);
+}
+
+macro_rules! impl_event {
+    ($sync:ident) => {
+        use core::{
+            fmt,
+            future::Future,
+            pin::Pin,
+            sync::atomic::Ordering,
+            task::{Context, Poll, Waker},
+        };
+
+        use crate::$sync::{atomic::AtomicUsize, shared::Shared};
+
+        /// An event that listeners can wait for, woken in the order they started
+        /// listening.
+        ///
+        /// A notified listener that is dropped before receiving its
+        /// notification passes it on to the next listener.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        #[doc = concat!("use synchrony::", stringify!($sync), "::event::Event;")]
+        /// let event = Event::new();
+        /// let mut first = event.listen();
+        /// let mut second = event.listen();
+        ///
+        /// event.notify(1);
+        /// assert!(first.is_notified());
+        /// assert!(!second.is_notified());
+        ///
+        /// event.notify_additional(1);
+        /// assert!(second.is_notified());
+        /// ```
+        ///
+        /// Listeners are futures:
+        ///
+        /// ```
+        /// # futures::executor::block_on(async {
+        #[doc = concat!("use synchrony::", stringify!($sync), "::event::Event;")]
+        /// let event = Event::new();
+        /// futures::join!(event.listen(), event.listen(), async {
+        ///     event.notify(usize::MAX)
+        /// });
+        /// # });
+        /// ```
+        ///
+        /// A dropped notification isn't lost:
+        ///
+        /// ```
+        #[doc = concat!("use synchrony::", stringify!($sync), "::event::Event;")]
+        /// let event = Event::new();
+        /// let first = event.listen();
+        /// let mut second = event.listen();
+        ///
+        /// event.notify(1);
+        /// assert!(!second.is_notified());
+        /// drop(first);
+        /// assert!(second.is_notified());
+        /// ```
+        #[derive(Debug)]
+        pub struct Event {
+            inner: RawEvent,
+            // Neither backend counts its listeners with the same semantics, so
+            // we do. Listeners may outlive the event.
+            listeners: Shared<AtomicUsize>,
+        }
+
+        impl Event {
+            /// Create a new [`Event`].
+            pub fn new() -> Self {
+                Self {
+                    inner: RawEvent::new(),
+                    listeners: Shared::new(AtomicUsize::new(0)),
+                }
+            }
+
+            /// Start listening for a notification.
+            pub fn listen(&self) -> EventListener {
+                self.listeners.fetch_add(1, Ordering::Relaxed);
+                EventListener {
+                    listener: Some(self.inner.listen()),
+                    listeners: self.listeners.clone(),
+                    waker: None,
+                }
+            }
+
+            /// Notify listeners until `n` of them are notified, counting those
+            /// that are notified but haven't received the notification yet.
+            ///
+            /// `usize::MAX` notifies all the listeners.
+            pub fn notify(&self, n: usize) {
+                self.inner.notify(n);
+            }
+
+            /// Notify `n` more listeners, regardless of those that are already
+            /// notified.
+            pub fn notify_additional(&self, n: usize) {
+                self.inner.notify_additional(n);
+            }
+
+            /// Number of listeners that haven't received a notification yet,
+            /// notified or not.
+            ///
+            /// # Example
+            ///
+            /// ```
+            #[doc = concat!("use synchrony::", stringify!($sync), "::event::Event;")]
+            ///
+            /// let event = Event::new();
+            /// let mut listener = event.listen();
+            /// assert_eq!(event.total_listeners(), 1);
+            /// event.notify(1);
+            /// assert_eq!(event.total_listeners(), 1);
+            /// assert!(listener.is_notified());
+            /// assert_eq!(event.total_listeners(), 0);
+            /// drop(listener);
+            /// assert_eq!(event.total_listeners(), 0);
+            /// ```
+            pub fn total_listeners(&self) -> usize {
+                self.listeners.load(Ordering::Relaxed)
+            }
+        }
+
+        impl Default for Event {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        /// A listener of an [`Event`], which resolves once it receives a
+        /// notification.
+        pub struct EventListener {
+            // `None` once the notification is received.
+            listener: Option<RawListener>,
+            // Counts the listener until its notification is received.
+            listeners: Shared<AtomicUsize>,
+            // The waker of the last poll, to not lose it in `is_notified`.
+            waker: Option<Waker>,
+        }
+
+        impl EventListener {
+            /// Returns whether this listener has been notified.
+            ///
+            /// Once it returns `true`, the notification is received as if the
+            /// listener had been awaited, so it won't be passed on when the
+            /// listener is dropped.
+            pub fn is_notified(&mut self) -> bool {
+                let waker = self.waker.clone().unwrap_or_else(|| Waker::noop().clone());
+                self.poll_listener(&mut Context::from_waker(&waker))
+                    .is_ready()
+            }
+
+            fn poll_listener(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+                let Some(listener) = &mut self.listener else {
+                    return Poll::Ready(());
+                };
+                let res = Pin::new(listener).poll(cx);
+                if res.is_ready() {
+                    self.listener = None;
+                    self.listeners.fetch_sub(1, Ordering::Relaxed);
+                }
+                res
+            }
+        }
+
+        impl Drop for EventListener {
+            fn drop(&mut self) {
+                if self.listener.is_some() {
+                    self.listeners.fetch_sub(1, Ordering::Relaxed);
+                }
+            }
+        }
+
+        impl Future for EventListener {
+            type Output = ();
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+                let this = self.get_mut();
+                match &this.waker {
+                    Some(waker) if waker.will_wake(cx.waker()) => {}
+                    _ => this.waker = Some(cx.waker().clone()),
+                }
+                this.poll_listener(cx)
+            }
+        }
+
+        impl fmt::Debug for EventListener {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("EventListener")
+                    .field("received", &self.listener.is_none())
+                    .finish()
+            }
+        }
+    };
+}
+
+use impl_event;
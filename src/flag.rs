@@ -1,22 +1,22 @@
 //! Boolean flags
 
-/// Multithreaded boolean flag based on [`std::sync::atomic::AtomicBool`]
+/// Multithreaded boolean flag based on [`core::sync::atomic::AtomicBool`]
 pub mod sync {
     super::impl_flag!(sync);
 
     impl crate::AssertMt for Flag {}
 }
 
-/// Singlethreaded boolean flag based on [`std::cell::Cell`]
+/// Singlethreaded boolean flag based on [`core::cell::Cell`]
 pub mod unsync {
     super::impl_flag!(unsync);
 }
 
 macro_rules! impl_flag {
     ($sync:ident) => {
-        use std::sync::atomic::Ordering;
+        use core::sync::atomic::Ordering;
 
-        use crate::$sync::atomic::AtomicBool;
+        use crate::$sync::{atomic::AtomicBool, spin::Backoff};
 
         /// A boolean flag
         #[derive(Debug)]
@@ -24,6 +24,15 @@ macro_rules! impl_flag {
 
         impl Flag {
             /// Create a new flag
+            #[cfg(not(loom))]
+            pub const fn new(val: bool) -> Self {
+                Flag(AtomicBool::new(val))
+            }
+
+            /// Create a new flag
+            ///
+            /// This `new` is not `const` due to loom not supporting it.
+            #[cfg(loom)]
             pub fn new(val: bool) -> Self {
                 Flag(AtomicBool::new(val))
             }
@@ -40,6 +49,7 @@ macro_rules! impl_flag {
 
             /// Flip the current value and return the new value
             pub fn flip(&self) -> bool {
+                let backoff = Backoff::new();
                 let mut current = self.get();
                 loop {
                     let new = !current;
@@ -52,9 +62,24 @@ macro_rules! impl_flag {
                         Ok(_) => return new,
                         Err(previous) => current = previous,
                     }
+                    backoff.spin();
                 }
             }
         }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for Flag {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.get().serialize(serializer)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for Flag {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                bool::deserialize(deserializer).map(Self::new)
+            }
+        }
     };
 }
 
@@ -37,33 +37,72 @@
 //! let unsync_lock = unsync::bilock::BiLock::new(42);
 //! let sync_counter = sync::atomic::AtomicUsize::new(42);
 //! ```
+//!
+//! # `no_std`
+//!
+//! This crate is `no_std` and only requires `alloc` when the default `std`
+//! feature is disabled. In that case, [`sync::mutex_blocking::Mutex`] is
+//! backed by a spinlock.
+#![no_std]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![warn(missing_docs)]
 #![deny(rustdoc::broken_intra_doc_links)]
 
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 #[cfg(feature = "async_flag")]
 mod async_flag;
 #[cfg(feature = "bilock")]
 mod bilock;
+// Only used by feature-gated async primitives.
+#[cfg(feature = "std")]
+#[allow(dead_code)]
+mod blocking;
 #[cfg(feature = "event")]
 mod event;
 #[cfg(feature = "mutex")]
 mod mutex;
+#[cfg(feature = "rate_limit")]
+mod rate_limit;
+#[cfg(feature = "reset_event")]
+mod reset_event;
+#[cfg(feature = "wait_group")]
+mod wait_group;
 #[cfg(feature = "waker_slot")]
 mod waker_slot;
+#[cfg(feature = "watch")]
+mod watch;
+
+// Parts of the bookkeeping are only used by feature-gated locks.
+#[cfg(feature = "deadlock-detection")]
+#[allow(dead_code)]
+pub mod deadlock;
+#[cfg(not(feature = "deadlock-detection"))]
+#[allow(dead_code)]
+mod deadlock;
+
+// Parts of the bookkeeping are only used by feature-gated locks.
+#[cfg(feature = "metrics")]
+#[allow(dead_code)]
+pub mod metrics;
+#[cfg(not(feature = "metrics"))]
+#[allow(dead_code)]
+mod metrics;
 
 mod atomic;
+mod cell;
 mod flag;
 mod mutex_blocking;
+mod once;
+mod per_thread;
+mod seqlock;
 mod shared;
+mod spin_lock;
 
 /// Multithreaded version of primitives
 pub mod sync {
-    /// Multithreaded `Watch` channel based on [`see`].
-    #[doc(inline)]
-    #[cfg(feature = "watch")]
-    pub use see::sync as watch;
-
     #[doc(inline)]
     #[cfg(feature = "async_flag")]
     pub use crate::async_flag::sync as async_flag;
@@ -77,22 +116,33 @@ pub mod sync {
     #[cfg(feature = "mutex")]
     pub use crate::mutex::sync as mutex;
     #[doc(inline)]
+    #[cfg(feature = "std")]
+    pub use crate::per_thread::sync as per_thread;
+    #[doc(inline)]
+    #[cfg(feature = "rate_limit")]
+    pub use crate::rate_limit::sync as rate_limit;
+    #[doc(inline)]
+    #[cfg(feature = "reset_event")]
+    pub use crate::reset_event::sync as reset_event;
+    #[doc(inline)]
+    #[cfg(feature = "wait_group")]
+    pub use crate::wait_group::sync as wait_group;
+    #[doc(inline)]
     #[cfg(feature = "waker_slot")]
     pub use crate::waker_slot::sync as waker_slot;
     #[doc(inline)]
+    #[cfg(feature = "watch")]
+    pub use crate::watch::sync as watch;
+    #[doc(inline)]
     pub use crate::{
         atomic::sync as atomic, flag::sync as flag, mutex_blocking::sync as mutex_blocking,
-        shared::sync as shared,
+        once::sync as once, seqlock::sync as seqlock, shared::sync as shared,
+        spin_lock::sync as spin,
     };
 }
 
 /// Singlethreaded version of primitives
 pub mod unsync {
-    /// Singlethreaded `Watch` channel based on [`see`].
-    #[doc(inline)]
-    #[cfg(feature = "watch")]
-    pub use see::unsync as watch;
-
     #[doc(inline)]
     #[cfg(feature = "async_flag")]
     pub use crate::async_flag::unsync as async_flag;
@@ -106,12 +156,25 @@ pub mod unsync {
     #[cfg(feature = "mutex")]
     pub use crate::mutex::unsync as mutex;
     #[doc(inline)]
+    #[cfg(feature = "rate_limit")]
+    pub use crate::rate_limit::unsync as rate_limit;
+    #[doc(inline)]
+    #[cfg(feature = "reset_event")]
+    pub use crate::reset_event::unsync as reset_event;
+    #[doc(inline)]
+    #[cfg(feature = "wait_group")]
+    pub use crate::wait_group::unsync as wait_group;
+    #[doc(inline)]
     #[cfg(feature = "waker_slot")]
     pub use crate::waker_slot::unsync as waker_slot;
     #[doc(inline)]
+    #[cfg(feature = "watch")]
+    pub use crate::watch::unsync as watch;
+    #[doc(inline)]
     pub use crate::{
         atomic::unsync as atomic, flag::unsync as flag, mutex_blocking::unsync as mutex_blocking,
-        shared::unsync as shared,
+        once::unsync as once, per_thread::unsync as per_thread, seqlock::unsync as seqlock,
+        shared::unsync as shared, spin_lock::unsync as spin,
     };
 }
 
@@ -119,14 +182,55 @@ pub mod unsync {
 #[allow(dead_code)]
 trait AssertMt: Send + Sync {}
 
+/// Switches an import to its [loom] or [shuttle] counterpart when compiled with
+/// `--cfg loom` or `--cfg shuttle`. loom takes precedence if both are set.
+///
+/// Shuttle doesn't model cells or `futures` types, so those imports only switch
+/// under loom.
+///
+/// [loom]: https://docs.rs/loom
+/// [shuttle]: https://docs.rs/shuttle
 macro_rules! cfg_loom {
     {
-        $vis:vis use $alt:ident :: $($tail:tt)*
+        $vis:vis use portable_atomic :: { $($item:ident),* $(,)? };
+    } => {
+        #[cfg(loom)]
+        $vis use loom::sync::atomic::{ $($item),* };
+
+        #[cfg(all(shuttle, not(loom)))]
+        $vis use shuttle::sync::atomic::{ $($item),* };
+
+        #[cfg(not(any(loom, shuttle)))]
+        $vis use portable_atomic::{ $($item),* };
+    };
+    {
+        $vis:vis use core :: cell :: $($tail:tt)*
+    } => {
+        #[cfg(loom)]
+        $vis use loom::cell::$($tail)*
+
+        #[cfg(not(loom))]
+        $vis use core::cell::$($tail)*
+    };
+    {
+        $vis:vis use futures_util :: $($tail:tt)*
     } => {
         #[cfg(loom)]
         $vis use loom::$($tail)*
 
         #[cfg(not(loom))]
+        $vis use futures_util::$($tail)*
+    };
+    {
+        $vis:vis use $alt:ident :: $($tail:tt)*
+    } => {
+        #[cfg(loom)]
+        $vis use loom::$($tail)*
+
+        #[cfg(all(shuttle, not(loom)))]
+        $vis use shuttle::$($tail)*
+
+        #[cfg(not(any(loom, shuttle)))]
         $vis use $alt::$($tail)*
     };
 }
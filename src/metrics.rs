@@ -0,0 +1,155 @@
+//! Contention metrics for locks.
+//!
+//! With the `metrics` feature enabled, the async `Mutex`, `BiLock` and the
+//! blocking `Mutex` count their acquisitions and how long tasks or threads had
+//! to wait for them. The counters can be read with their `stats` method, which
+//! returns a [`LockStats`] snapshot.
+//!
+//! Without the feature, all the bookkeeping compiles down to nothing.
+
+#[cfg(feature = "metrics")]
+pub use imp::LockStats;
+pub(crate) use imp::{Metrics, WaitStart};
+
+#[cfg(feature = "metrics")]
+mod imp {
+    use core::{
+        sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+        time::Duration,
+    };
+    use std::time::Instant;
+
+    /// A snapshot of the contention counters of a lock.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use synchrony::sync::mutex_blocking::Mutex;
+    ///
+    /// let lock = Mutex::new(0);
+    /// *lock.lock() += 1;
+    /// *lock.lock() += 1;
+    ///
+    /// let stats = lock.stats();
+    /// assert_eq!(stats.acquisitions, 2);
+    /// assert_eq!(stats.contended, 0);
+    /// ```
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    #[non_exhaustive]
+    pub struct LockStats {
+        /// Number of times the lock has been acquired.
+        pub acquisitions: u64,
+        /// Number of acquisitions that had to wait for the lock.
+        pub contended: u64,
+        /// Total time spent waiting for the lock.
+        pub total_wait: Duration,
+        /// Longest time spent waiting for the lock.
+        pub max_wait: Duration,
+        /// Number of tasks or threads currently waiting for the lock.
+        pub waiters: usize,
+        /// Highest number of tasks or threads waiting for the lock at once.
+        pub peak_waiters: usize,
+    }
+
+    /// Start of a wait for a lock, if any.
+    #[derive(Debug)]
+    pub(crate) struct WaitStart(Option<Instant>);
+
+    impl WaitStart {
+        pub const fn new() -> Self {
+            Self(None)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    pub(crate) struct Metrics {
+        acquisitions: AtomicU64,
+        contended: AtomicU64,
+        total_wait_ns: AtomicU64,
+        max_wait_ns: AtomicU64,
+        waiters: AtomicUsize,
+        peak_waiters: AtomicUsize,
+    }
+
+    impl Metrics {
+        pub const fn new() -> Self {
+            Self {
+                acquisitions: AtomicU64::new(0),
+                contended: AtomicU64::new(0),
+                total_wait_ns: AtomicU64::new(0),
+                max_wait_ns: AtomicU64::new(0),
+                waiters: AtomicUsize::new(0),
+                peak_waiters: AtomicUsize::new(0),
+            }
+        }
+
+        /// Start waiting for the lock, unless `wait` is already waiting.
+        pub fn wait(&self, wait: &mut WaitStart) {
+            if wait.0.is_none() {
+                wait.0 = Some(Instant::now());
+                self.contended.fetch_add(1, Ordering::Relaxed);
+                let waiters = self.waiters.fetch_add(1, Ordering::Relaxed) + 1;
+                self.peak_waiters.fetch_max(waiters, Ordering::Relaxed);
+            }
+        }
+
+        /// Record an acquisition, ending the wait if there is one.
+        pub fn acquire(&self, wait: &mut WaitStart) {
+            self.acquisitions.fetch_add(1, Ordering::Relaxed);
+            if let Some(start) = wait.0.take() {
+                self.waiters.fetch_sub(1, Ordering::Relaxed);
+                let waited = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+                self.total_wait_ns.fetch_add(waited, Ordering::Relaxed);
+                self.max_wait_ns.fetch_max(waited, Ordering::Relaxed);
+            }
+        }
+
+        /// Give up waiting for the lock.
+        pub fn cancel(&self, wait: &mut WaitStart) {
+            if wait.0.take().is_some() {
+                self.waiters.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+
+        pub fn stats(&self) -> LockStats {
+            LockStats {
+                acquisitions: self.acquisitions.load(Ordering::Relaxed),
+                contended: self.contended.load(Ordering::Relaxed),
+                total_wait: Duration::from_nanos(self.total_wait_ns.load(Ordering::Relaxed)),
+                max_wait: Duration::from_nanos(self.max_wait_ns.load(Ordering::Relaxed)),
+                waiters: self.waiters.load(Ordering::Relaxed),
+                peak_waiters: self.peak_waiters.load(Ordering::Relaxed),
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+mod imp {
+    #[derive(Debug)]
+    pub(crate) struct WaitStart;
+
+    impl WaitStart {
+        pub const fn new() -> Self {
+            Self
+        }
+    }
+
+    #[derive(Debug, Default)]
+    pub(crate) struct Metrics;
+
+    impl Metrics {
+        pub const fn new() -> Self {
+            Self
+        }
+
+        #[inline(always)]
+        pub fn wait(&self, _: &mut WaitStart) {}
+
+        #[inline(always)]
+        pub fn acquire(&self, _: &mut WaitStart) {}
+
+        #[inline(always)]
+        pub fn cancel(&self, _: &mut WaitStart) {}
+    }
+}
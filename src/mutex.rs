@@ -1,11 +1,19 @@
 //! Async Mutex lock
 //!
 //! The implementation is copied from [`futures::lock`] with our own
-//! synchronization primitives.
+//! synchronization primitives. Both flavors share it, on top of their own
+//! lock state and queue of waiters.
 
 /// Multithreaded async Mutex
 pub mod sync {
-    use crate::AssertMt;
+    use core::sync::atomic::Ordering;
+
+    use crate::{
+        AssertMt,
+        sync::{atomic::AtomicUsize, mutex_blocking::RawMutex as BlockingMutex},
+    };
+
+    pub use super::{LockAll, lock_all, try_lock_all};
 
     super::impl_mutex!(sync);
 
@@ -40,65 +48,725 @@ pub mod sync {
     unsafe impl<T: ?Sized + Sync, U: ?Sized + Sync> Sync for MappedMutexGuard<'_, T, U> {}
 
     impl<T: Send> AssertMt for Mutex<T> {}
+
+    #[cfg(feature = "std")]
+    impl<T: ?Sized> Mutex<T> {
+        /// Acquire the lock from synchronous code, parking the thread until it
+        /// is acquired.
+        ///
+        /// # Async contexts
+        ///
+        /// Calling it from an async task blocks the executor thread, and
+        /// deadlocks if the lock is held by a task of that thread. This can't be
+        /// detected: use [`lock`](Self::lock) there instead.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use std::{sync::Arc, thread};
+        ///
+        /// use synchrony::sync::mutex::Mutex;
+        ///
+        /// let mutex = Arc::new(Mutex::new(0));
+        /// let guard = mutex.try_lock().unwrap();
+        /// let handle = thread::spawn({
+        ///     let mutex = mutex.clone();
+        ///     move || *mutex.blocking_lock() += 1
+        /// });
+        /// drop(guard);
+        /// handle.join().unwrap();
+        /// assert_eq!(*mutex.blocking_lock(), 1);
+        /// ```
+        #[track_caller]
+        pub fn blocking_lock(&self) -> MutexGuard<'_, T> {
+            crate::blocking::block_on(self.lock())
+        }
+
+        /// Acquire the lock from synchronous code, parking the thread until it
+        /// is acquired.
+        ///
+        /// See [`blocking_lock`](Self::blocking_lock) for detail.
+        #[cfg(not(loom))]
+        #[track_caller]
+        pub fn blocking_lock_owned(self: Shared<Self>) -> OwnedMutexGuard<T> {
+            crate::blocking::block_on(self.lock_owned())
+        }
+    }
+
+    const IS_LOCKED: usize = 1 << 0;
+    const HAS_WAITERS: usize = 1 << 1;
+
+    // The lock is a flag of an atomic state, so that it can be taken without
+    // locking the queue, which is only done when there are waiters.
+    struct RawMutex {
+        state: AtomicUsize,
+        waiters: BlockingMutex<Waiters>,
+    }
+
+    impl RawMutex {
+        #[cfg(not(loom))]
+        const fn new() -> Self {
+            Self {
+                state: AtomicUsize::new(0),
+                waiters: BlockingMutex::new(Waiters::new()),
+            }
+        }
+
+        #[cfg(loom)]
+        fn new() -> Self {
+            Self {
+                state: AtomicUsize::new(0),
+                waiters: BlockingMutex::new(Waiters::new()),
+            }
+        }
+
+        #[inline]
+        fn is_locked(&self) -> bool {
+            self.state.load(Ordering::Acquire) & IS_LOCKED != 0
+        }
+
+        #[inline]
+        fn has_waiters(&self) -> bool {
+            self.state.load(Ordering::Acquire) & HAS_WAITERS != 0
+        }
+
+        #[inline]
+        fn try_acquire(&self) -> bool {
+            let old_state = self.state.fetch_or(IS_LOCKED, Ordering::Acquire);
+            (old_state & IS_LOCKED) == 0
+        }
+
+        // Unlock, returning the waker of the next waiter to wake, if any.
+        #[inline]
+        fn release(&self) -> Option<Waker> {
+            let old_state = self.state.fetch_and(!IS_LOCKED, Ordering::AcqRel);
+            if (old_state & HAS_WAITERS) != 0 {
+                self.waiters.lock().take_next()
+            } else {
+                None
+            }
+        }
+
+        // Hand the lock over to the next waiter, or unlock if there is none,
+        // returning the waker to wake, if any.
+        fn hand_over(&self) -> Option<Waker> {
+            self.hand_next(&mut self.waiters.lock())
+        }
+
+        fn hand_next(&self, waiters: &mut Waiters) -> Option<Waker> {
+            if waiters.is_empty() {
+                // The queue is locked, so no waiter can queue up in between.
+                self.state.fetch_and(!IS_LOCKED, Ordering::Release);
+                None
+            } else {
+                waiters.hand_next()
+            }
+        }
+
+        // Queue a node, or update its waker if it's queued already. Returns
+        // whether the lock was handed over to the node, which is then removed.
+        //
+        // # Safety
+        //
+        // The node must stay pinned until it is removed.
+        unsafe fn enqueue(&self, node: &Node, queued: bool, priority: u32, waker: &Waker) -> bool {
+            let mut waiters = self.waiters.lock();
+            let replaced = if !queued {
+                unsafe { waiters.insert(node, priority, waker) };
+                if waiters.len() == 1 {
+                    self.state.fetch_or(HAS_WAITERS, Ordering::Relaxed); // released by mutex unlock
+                }
+                None
+            } else if unsafe { waiters.is_acquired(node) } {
+                unsafe { waiters.remove(node) };
+                if waiters.is_empty() {
+                    self.state.fetch_and(!HAS_WAITERS, Ordering::Relaxed); // released by mutex unlock
+                }
+                return true;
+            } else {
+                unsafe { waiters.register(node, waker) }
+            };
+            // Dropping a waker may reenter the mutex, so only once the queue
+            // is released.
+            drop(waiters);
+            drop(replaced);
+            false
+        }
+
+        // Remove a queued node. If it had been woken, and `wake_another` is
+        // set, or if the lock had been handed over to it, return the waker of
+        // the next waiter to wake instead.
+        //
+        // # Safety
+        //
+        // The node must be queued.
+        unsafe fn dequeue(&self, node: &Node, wake_another: bool) -> Option<Waker> {
+            let mut waiters = self.waiters.lock();
+            let waiter = unsafe { waiters.remove(node) };
+            if waiters.is_empty() {
+                self.state.fetch_and(!HAS_WAITERS, Ordering::Relaxed); // released by mutex unlock
+            }
+            let next = match waiter {
+                // We were awoken, but then dropped before we could wake up to
+                // acquire the lock. Wake up another waiter.
+                Waiter::Woken if wake_another => waiters.take_next(),
+                // We were dropped while holding the lock, pass it on.
+                Waiter::Acquired => self.hand_next(&mut waiters),
+                _ => None,
+            };
+            // As in `enqueue`.
+            drop(waiters);
+            drop(waiter);
+            next
+        }
+    }
 }
 
 /// Singlethreaded async Mutex
+///
+/// Unlike the multithreaded one, it doesn't need to lock its queue of waiters,
+/// so locking is just flipping a [`Cell`](core::cell::Cell).
 pub mod unsync {
+    use core::cell::Cell;
+
+    pub use super::{LockAll, lock_all, try_lock_all};
+
     super::impl_mutex!(unsync);
+
+    // The queue is only accessed from the thread of the mutex, and never while
+    // a waker is woken or dropped, so it needs no locking.
+    struct RawMutex {
+        locked: Cell<bool>,
+        waiters: UnsafeCell<Waiters>,
+    }
+
+    impl RawMutex {
+        #[cfg(not(loom))]
+        const fn new() -> Self {
+            Self {
+                locked: Cell::new(false),
+                waiters: UnsafeCell::new(Waiters::new()),
+            }
+        }
+
+        #[cfg(loom)]
+        fn new() -> Self {
+            Self {
+                locked: Cell::new(false),
+                waiters: UnsafeCell::new(Waiters::new()),
+            }
+        }
+
+        #[inline]
+        fn waiters<R>(&self, f: impl FnOnce(&mut Waiters) -> R) -> R {
+            // SAFETY: The mutex isn't `Sync`, and `f` doesn't reenter it: the
+            // wakers it takes out of the queue, to wake or replaced, are
+            // returned to be woken or dropped after it returns.
+            self.waiters.with_mut(|waiters| f(unsafe { &mut *waiters }))
+        }
+
+        #[inline]
+        fn is_locked(&self) -> bool {
+            self.locked.get()
+        }
+
+        #[inline]
+        fn has_waiters(&self) -> bool {
+            self.waiters(|waiters| !waiters.is_empty())
+        }
+
+        #[inline]
+        fn try_acquire(&self) -> bool {
+            !self.locked.replace(true)
+        }
+
+        // Unlock, returning the waker of the next waiter to wake, if any.
+        #[inline]
+        fn release(&self) -> Option<Waker> {
+            self.locked.set(false);
+            self.waiters(Waiters::take_next)
+        }
+
+        // Hand the lock over to the next waiter, or unlock if there is none,
+        // returning the waker to wake, if any.
+        fn hand_over(&self) -> Option<Waker> {
+            self.waiters(|waiters| self.hand_next(waiters))
+        }
+
+        fn hand_next(&self, waiters: &mut Waiters) -> Option<Waker> {
+            if waiters.is_empty() {
+                self.locked.set(false);
+                None
+            } else {
+                waiters.hand_next()
+            }
+        }
+
+        // Queue a node, or update its waker if it's queued already. Returns
+        // whether the lock was handed over to the node, which is then removed.
+        //
+        // # Safety
+        //
+        // The node must stay pinned until it is removed.
+        unsafe fn enqueue(&self, node: &Node, queued: bool, priority: u32, waker: &Waker) -> bool {
+            let (acquired, replaced) = self.waiters(|waiters| {
+                if !queued {
+                    unsafe { waiters.insert(node, priority, waker) };
+                    (false, None)
+                } else if unsafe { waiters.is_acquired(node) } {
+                    unsafe { waiters.remove(node) };
+                    (true, None)
+                } else {
+                    (false, unsafe { waiters.register(node, waker) })
+                }
+            });
+            drop(replaced);
+            acquired
+        }
+
+        // Remove a queued node. If it had been woken, and `wake_another` is
+        // set, or if the lock had been handed over to it, return the waker of
+        // the next waiter to wake instead.
+        //
+        // # Safety
+        //
+        // The node must be queued.
+        unsafe fn dequeue(&self, node: &Node, wake_another: bool) -> Option<Waker> {
+            let (waiter, next) = self.waiters(|waiters| {
+                let waiter = unsafe { waiters.remove(node) };
+                let next = match waiter {
+                    Waiter::Woken if wake_another => waiters.take_next(),
+                    Waiter::Acquired => self.hand_next(waiters),
+                    _ => None,
+                };
+                (waiter, next)
+            });
+            drop(waiter);
+            next
+        }
+    }
 }
 
-use std::{mem, task::Waker};
+use alloc::boxed::Box;
+use core::{future::Future, marker::PhantomPinned, mem, ptr::NonNull, task::Waker};
+
+use crate::cell::UnsafeCell;
 
 enum Waiter {
     Waiting(Waker),
     Woken,
+    // The lock was handed over to the waiter, which holds it already.
+    Acquired,
 }
 
 impl Waiter {
-    fn register(&mut self, waker: &Waker) {
+    // Update the waker, returning the replaced one. Dropping it may reenter
+    // the mutex, so it's dropped by the caller once the queue is released.
+    fn register(&mut self, waker: &Waker) -> Option<Waker> {
+        match self {
+            Self::Waiting(w) if waker.will_wake(w) => None,
+            _ => self.replace(Self::Waiting(waker.clone())),
+        }
+    }
+
+    // Mark the waiter as woken, returning its waker if it wasn't already. A
+    // waiter which was handed the lock over has been woken for it already.
+    fn take(&mut self) -> Option<Waker> {
         match self {
-            Self::Waiting(w) if waker.will_wake(w) => {}
-            _ => *self = Self::Waiting(waker.clone()),
+            Self::Acquired => None,
+            _ => self.replace(Self::Woken),
         }
     }
 
-    fn wake(&mut self) {
-        match mem::replace(self, Self::Woken) {
-            Self::Waiting(waker) => waker.wake(),
-            Self::Woken => {}
+    // Hand the lock over to the waiter, returning its waker if it wasn't woken
+    // already.
+    fn hand(&mut self) -> Option<Waker> {
+        self.replace(Self::Acquired)
+    }
+
+    fn replace(&mut self, waiter: Self) -> Option<Waker> {
+        match mem::replace(self, waiter) {
+            Self::Waiting(waker) => Some(waker),
+            Self::Woken | Self::Acquired => None,
         }
     }
 }
 
-const IS_LOCKED: usize = 1 << 0;
-const HAS_WAITERS: usize = 1 << 1;
+// A waiter of a mutex, linked into the queue of the mutex while it waits. Lock
+// futures only allocate theirs once they have to wait, and keep it boxed so
+// that they can move freely.
+struct Node {
+    // Only accessed with the queue locked.
+    links: UnsafeCell<Links>,
+    _pinned: PhantomPinned,
+}
 
-// Sentinel for when no slot in the `Slab` has been dedicated to this object.
-const WAIT_KEY_NONE: usize = usize::MAX;
+struct Links {
+    prev: Option<NonNull<Node>>,
+    next: Option<NonNull<Node>>,
+    priority: u32,
+    waiter: Waiter,
+}
+
+impl Node {
+    #[cfg(not(loom))]
+    const fn new() -> Self {
+        Self {
+            links: UnsafeCell::new(Links::new()),
+            _pinned: PhantomPinned,
+        }
+    }
+
+    #[cfg(loom)]
+    fn new() -> Self {
+        Self {
+            links: UnsafeCell::new(Links::new()),
+            _pinned: PhantomPinned,
+        }
+    }
+}
+
+impl Links {
+    const fn new() -> Self {
+        Self {
+            prev: None,
+            next: None,
+            priority: 0,
+            waiter: Waiter::Woken,
+        }
+    }
+}
+
+// Access the links of a node.
+//
+// # Safety
+//
+// The node must be alive, and the queue it belongs to must be locked.
+unsafe fn links<R>(node: NonNull<Node>, f: impl FnOnce(&mut Links) -> R) -> R {
+    unsafe { node.as_ref() }
+        .links
+        .with_mut(|links| f(unsafe { &mut *links }))
+}
+
+// The waiters of a mutex, as an intrusive doubly-linked list sorted by
+// priority, then in FIFO order. The next waiter to wake is always at the head.
+//
+// A node is only linked and unlinked by the future which owns it, and which
+// unlinks it before dropping it. Woken nodes keep their place until then.
+struct Waiters {
+    head: Option<NonNull<Node>>,
+    tail: Option<NonNull<Node>>,
+    len: usize,
+}
+
+// SAFETY: The nodes are only linked while their futures borrow the mutex, so the
+// queue is always empty when the mutex itself can be sent to another thread.
+// Otherwise, it's only accessed with the queue locked, or from the thread of an
+// unsync mutex.
+unsafe impl Send for Waiters {}
+
+impl Waiters {
+    const fn new() -> Self {
+        Self {
+            head: None,
+            tail: None,
+            len: 0,
+        }
+    }
+
+    // Link a node behind the last one with the same or a higher priority.
+    //
+    // # Safety
+    //
+    // The node must not be linked already, and must stay pinned until it is
+    // removed.
+    unsafe fn insert(&mut self, node: &Node, priority: u32, waker: &Waker) {
+        let node = NonNull::from(node);
+        // Waiters usually share the same priority, in which case this stops
+        // at the tail.
+        let mut prev = self.tail;
+        while let Some(p) = prev {
+            match unsafe { links(p, |links| (links.priority >= priority, links.prev)) } {
+                (true, _) => break,
+                (false, p) => prev = p,
+            }
+        }
+        let next = match prev {
+            Some(p) => unsafe { links(p, |links| links.next.replace(node)) },
+            None => self.head.replace(node),
+        };
+        match next {
+            Some(n) => unsafe { links(n, |links| links.prev = Some(node)) },
+            None => self.tail = Some(node),
+        }
+        unsafe {
+            links(node, |links| {
+                *links = Links {
+                    prev,
+                    next,
+                    priority,
+                    waiter: Waiter::Waiting(waker.clone()),
+                }
+            })
+        };
+        self.len += 1;
+    }
+
+    // Returns whether the lock was handed over to the node.
+    //
+    // # Safety
+    //
+    // The node must be linked.
+    unsafe fn is_acquired(&mut self, node: &Node) -> bool {
+        unsafe {
+            links(NonNull::from(node), |links| {
+                matches!(links.waiter, Waiter::Acquired)
+            })
+        }
+    }
+
+    // Update the waker of a node, returning the replaced one to drop once the
+    // queue is released.
+    //
+    // # Safety
+    //
+    // The node must be linked, and the lock must not be handed over to it.
+    unsafe fn register(&mut self, node: &Node, waker: &Waker) -> Option<Waker> {
+        unsafe { links(NonNull::from(node), |links| links.waiter.register(waker)) }
+    }
+
+    // Unlink a node, returning its waiter to drop once the queue is released.
+    //
+    // # Safety
+    //
+    // The node must be linked.
+    unsafe fn remove(&mut self, node: &Node) -> Waiter {
+        let (prev, next, waiter) = unsafe {
+            links(NonNull::from(node), |links| {
+                let Links {
+                    prev, next, waiter, ..
+                } = mem::replace(links, Links::new());
+                (prev, next, waiter)
+            })
+        };
+        match prev {
+            Some(p) => unsafe { links(p, |links| links.next = next) },
+            None => self.head = next,
+        }
+        match next {
+            Some(n) => unsafe { links(n, |links| links.prev = prev) },
+            None => self.tail = prev,
+        }
+        self.len -= 1;
+        waiter
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Mark the waiter with the highest priority as woken, the oldest first
+    // among equals, and return its waker. It may have been woken already, in
+    // which case it's on its way. The waker is woken by the caller once the
+    // queue is released, as waking may reenter the mutex.
+    fn take_next(&mut self) -> Option<Waker> {
+        let head = self.head?;
+        unsafe { links(head, |links| links.waiter.take()) }
+    }
+
+    // Hand the lock over to the same waiter as `take_next`, returning its
+    // waker if it wasn't woken already.
+    fn hand_next(&mut self) -> Option<Waker> {
+        let head = self.head?;
+        unsafe { links(head, |links| links.waiter.hand()) }
+    }
+}
+
+#[track_caller]
+fn assert_locked(locked: bool) {
+    assert!(locked, "the guard was used after `unlocked` was cancelled");
+}
+
+/// Acquire several mutexes without risking a deadlock with another task
+/// doing the same in a different order.
+///
+/// The mutexes are acquired one at a time, in the order of their addresses,
+/// which is the same for every caller. The guards are returned in the order of
+/// `locks`. See [`LockAll`] for the supported tuples.
+///
+/// This only protects against other callers of `lock_all`: a task locking the
+/// same mutexes one by one must still take them in a consistent order.
+///
+/// # Panics
+///
+/// Panics if the same mutex appears twice in `locks`.
+///
+/// # Example
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use synchrony::sync::{
+///     mutex::{Mutex, lock_all},
+///     shared::Shared,
+/// };
+///
+/// let alice = Mutex::new(100);
+/// let bob = Shared::new(Mutex::new(0));
+///
+/// // A transfer the other way around can't deadlock with this one.
+/// let (mut from, mut to) = lock_all((&alice, bob.clone())).await;
+/// *from -= 30;
+/// *to += 30;
+/// # });
+/// ```
+#[track_caller]
+pub fn lock_all<L: LockAll>(locks: L) -> impl Future<Output = L::Guards> {
+    locks.lock_all()
+}
+
+/// Attempt to acquire several mutexes immediately.
+///
+/// Fails if any of them is held, in which case none of them is. See
+/// [`lock_all`] for detail.
+///
+/// # Panics
+///
+/// Panics if the same mutex appears twice in `locks`.
+///
+/// # Example
+///
+/// ```
+/// use synchrony::unsync::mutex::{Mutex, try_lock_all};
+///
+/// let (a, b) = (Mutex::new(1), Mutex::new(2));
+/// let guard = b.try_lock().unwrap();
+/// assert!(try_lock_all((&a, &b)).is_none());
+/// assert!(a.try_lock().is_some());
+///
+/// drop(guard);
+/// let (a, b) = try_lock_all((&a, &b)).unwrap();
+/// assert_eq!(*a + *b, 3);
+/// ```
+#[track_caller]
+pub fn try_lock_all<L: LockAll>(locks: L) -> Option<L::Guards> {
+    locks.try_lock_all()
+}
+
+/// A tuple of mutexes that [`lock_all`] and [`try_lock_all`] can acquire.
+///
+/// It is implemented for tuples of up to 8 mutexes of either flavor, each of
+/// which is either a `&Mutex<T>`, acquired as a `MutexGuard<'_, T>`, or a
+/// `Shared<Mutex<T>>`, acquired as an `OwnedMutexGuard<T>`. The latter isn't
+/// available under loom.
+pub trait LockAll: sealed::LockAll {}
+
+impl<L: sealed::LockAll> LockAll for L {}
+
+mod sealed {
+    use core::future::Future;
+
+    // A mutex as it is passed to `lock_all`.
+    pub trait Lockable {
+        type Guard;
+
+        fn addr(&self) -> usize;
+
+        fn lock(self) -> impl Future<Output = Self::Guard>;
+
+        fn try_lock(self) -> Option<Self::Guard>;
+    }
+
+    pub trait LockAll {
+        type Guards;
+
+        fn lock_all(self) -> impl Future<Output = Self::Guards>;
+
+        fn try_lock_all(self) -> Option<Self::Guards>;
+    }
+
+    // The indices of the mutexes, sorted by address.
+    #[track_caller]
+    fn order<const N: usize>(addrs: [usize; N]) -> [usize; N] {
+        let mut order = core::array::from_fn(|i| i);
+        order.sort_unstable_by_key(|&i| addrs[i]);
+        assert!(
+            order.windows(2).all(|w| addrs[w[0]] != addrs[w[1]]),
+            "the same mutex can't be locked twice"
+        );
+        order
+    }
+
+    macro_rules! impl_lock_all {
+        ($($l:ident $i:tt),+) => {
+            impl<$($l: Lockable),+> LockAll for ($($l,)+) {
+                type Guards = ($($l::Guard,)+);
+
+                #[track_caller]
+                fn lock_all(self) -> impl Future<Output = Self::Guards> {
+                    let order = order([$(self.$i.addr()),+]);
+                    // Create the futures here to record the caller.
+                    let mut futures = ($(Some(self.$i.lock()),)+);
+                    async move {
+                        let mut guards = ($(None::<$l::Guard>,)+);
+                        for i in order {
+                            match i {
+                                $($i => guards.$i = Some(futures.$i.take().unwrap().await),)+
+                                _ => unreachable!(),
+                            }
+                        }
+                        ($(guards.$i.unwrap(),)+)
+                    }
+                }
+
+                #[track_caller]
+                fn try_lock_all(self) -> Option<Self::Guards> {
+                    let order = order([$(self.$i.addr()),+]);
+                    let mut locks = ($(Some(self.$i),)+);
+                    let mut guards = ($(None::<$l::Guard>,)+);
+                    for i in order {
+                        match i {
+                            $($i => guards.$i = Some(locks.$i.take().unwrap().try_lock()?),)+
+                            _ => unreachable!(),
+                        }
+                    }
+                    Some(($(guards.$i.unwrap(),)+))
+                }
+            }
+        };
+    }
+
+    impl_lock_all!(A 0);
+    impl_lock_all!(A 0, B 1);
+    impl_lock_all!(A 0, B 1, C 2);
+    impl_lock_all!(A 0, B 1, C 2, D 3);
+    impl_lock_all!(A 0, B 1, C 2, D 3, E 4);
+    impl_lock_all!(A 0, B 1, C 2, D 3, E 4, F 5);
+    impl_lock_all!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+    impl_lock_all!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+}
 
 macro_rules! impl_mutex {
     ($sync:ident) => {
-        use std::{
+        use core::{
             fmt,
             future::Future,
             marker::PhantomData,
             mem,
             ops::{Deref, DerefMut},
             pin::Pin,
-            sync::atomic::Ordering,
             task::{Context, Poll},
         };
 
-        use slab::Slab;
-
         use super::*;
 
-        crate::cfg_loom! {
-            use std::cell::UnsafeCell;
-        }
-
-        use crate::$sync::{
-            atomic::AtomicUsize, mutex_blocking::Mutex as BlockingMutex, shared::Shared,
+        use crate::{
+            $sync::shared::Shared,
+            deadlock::{LockId, Owner, Site},
+            metrics::{Metrics, WaitStart},
         };
 
         /// A futures-aware mutex.
@@ -109,22 +777,47 @@ macro_rules! impl_mutex {
         /// in the order that they requested the lock, and it's possible for a single
         /// task which repeatedly takes the lock to starve other tasks, which may be
         /// left waiting indefinitely.
+        ///
+        /// # Priority
+        ///
+        /// On unlock, the waiter with the highest priority (see
+        /// [`lock_with_priority`](Self::lock_with_priority)) is woken, the one that
+        /// has waited the longest among equals. This only decides who is woken: a
+        /// task that isn't waiting yet may still take the lock first.
         pub struct Mutex<T: ?Sized> {
-            state: AtomicUsize,
-            waiters: BlockingMutex<Slab<Waiter>>,
+            raw: RawMutex,
+            id: LockId,
+            metrics: Metrics,
             value: UnsafeCell<T>,
         }
 
         impl<T: ?Sized> fmt::Debug for Mutex<T> {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                let state = self.state.load(Ordering::SeqCst);
                 f.debug_struct("Mutex")
-                    .field("is_locked", &((state & IS_LOCKED) != 0))
-                    .field("has_waiters", &((state & HAS_WAITERS) != 0))
+                    .field("is_locked", &self.raw.is_locked())
+                    .field("has_waiters", &self.raw.has_waiters())
                     .finish()
             }
         }
 
+        /// Serializes the value if the mutex isn't locked, or fails otherwise.
+        #[cfg(feature = "serde")]
+        impl<T: ?Sized + serde::Serialize> serde::Serialize for Mutex<T> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match self.try_lock() {
+                    Some(value) => value.serialize(serializer),
+                    None => Err(serde::ser::Error::custom("mutex is locked")),
+                }
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Mutex<T> {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                T::deserialize(deserializer).map(Self::new)
+            }
+        }
+
         impl<T> From<T> for Mutex<T> {
             fn from(t: T) -> Self {
                 Self::new(t)
@@ -139,10 +832,25 @@ macro_rules! impl_mutex {
 
         impl<T> Mutex<T> {
             /// Creates a new futures-aware mutex.
+            #[cfg(not(loom))]
+            pub const fn new(t: T) -> Self {
+                Self {
+                    raw: RawMutex::new(),
+                    id: LockId::new(),
+                    metrics: Metrics::new(),
+                    value: UnsafeCell::new(t),
+                }
+            }
+
+            /// Creates a new futures-aware mutex.
+            ///
+            /// This `new` is not `const` due to loom not supporting it.
+            #[cfg(loom)]
             pub fn new(t: T) -> Self {
                 Self {
-                    state: AtomicUsize::new(0),
-                    waiters: BlockingMutex::new(Slab::new()),
+                    raw: RawMutex::new(),
+                    id: LockId::new(),
+                    metrics: Metrics::new(),
                     value: UnsafeCell::new(t),
                 }
             }
@@ -157,10 +865,15 @@ macro_rules! impl_mutex {
             /// Attempt to acquire the lock immediately.
             ///
             /// If the lock is currently held, this will return `None`.
+            #[track_caller]
             pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
-                let old_state = self.state.fetch_or(IS_LOCKED, Ordering::Acquire);
-                if (old_state & IS_LOCKED) == 0 {
-                    Some(MutexGuard { mutex: self })
+                if self.try_acquire() {
+                    self.id.acquire(Owner::thread(), Site::caller());
+                    self.metrics.acquire(&mut WaitStart::new());
+                    Some(MutexGuard {
+                        mutex: self,
+                        locked: true,
+                    })
                 } else {
                     None
                 }
@@ -169,11 +882,15 @@ macro_rules! impl_mutex {
             /// Attempt to acquire the lock immediately.
             ///
             /// If the lock is currently held, this will return `None`.
+            #[cfg(not(loom))]
+            #[track_caller]
             pub fn try_lock_owned(self: &Shared<Self>) -> Option<OwnedMutexGuard<T>> {
-                let old_state = self.state.fetch_or(IS_LOCKED, Ordering::Acquire);
-                if (old_state & IS_LOCKED) == 0 {
+                if self.try_acquire() {
+                    self.id.acquire(Owner::thread(), Site::caller());
+                    self.metrics.acquire(&mut WaitStart::new());
                     Some(OwnedMutexGuard {
                         mutex: self.clone(),
+                        locked: true,
                     })
                 } else {
                     None
@@ -184,10 +901,34 @@ macro_rules! impl_mutex {
             ///
             /// This method returns a future that will resolve once the lock has been
             /// successfully acquired.
+            #[track_caller]
             pub fn lock(&self) -> MutexLockFuture<'_, T> {
+                self.lock_with_priority(0)
+            }
+
+            /// Acquire the lock asynchronously, with the given priority.
+            ///
+            /// While waiting, tasks with a higher `priority` are woken before those
+            /// with a lower one. [`lock`](Self::lock) uses a priority of `0`.
+            ///
+            /// # Example
+            ///
+            /// ```
+            /// # futures::executor::block_on(async {
+            #[doc = concat!("use synchrony::", stringify!($sync), "::mutex::Mutex;")]
+            ///
+            /// let mutex = Mutex::new(());
+            /// let _guard = mutex.lock_with_priority(10).await;
+            /// # });
+            /// ```
+            #[track_caller]
+            pub fn lock_with_priority(&self, priority: u32) -> MutexLockFuture<'_, T> {
                 MutexLockFuture {
                     mutex: Some(self),
-                    wait_key: WAIT_KEY_NONE,
+                    node: None,
+                    priority,
+                    site: Site::caller(),
+                    wait: WaitStart::new(),
                 }
             }
 
@@ -195,10 +936,27 @@ macro_rules! impl_mutex {
             ///
             /// This method returns a future that will resolve once the lock has been
             /// successfully acquired.
+            #[cfg(not(loom))]
+            #[track_caller]
             pub fn lock_owned(self: Shared<Self>) -> OwnedMutexLockFuture<T> {
+                self.lock_owned_with_priority(0)
+            }
+
+            /// Acquire the lock asynchronously, with the given priority.
+            ///
+            /// See [`lock_with_priority`](Self::lock_with_priority) for detail.
+            #[cfg(not(loom))]
+            #[track_caller]
+            pub fn lock_owned_with_priority(
+                self: Shared<Self>,
+                priority: u32,
+            ) -> OwnedMutexLockFuture<T> {
                 OwnedMutexLockFuture {
                     mutex: Some(self),
-                    wait_key: WAIT_KEY_NONE,
+                    node: None,
+                    priority,
+                    site: Site::caller(),
+                    wait: WaitStart::new(),
                 }
             }
 
@@ -209,50 +967,160 @@ macro_rules! impl_mutex {
             pub fn get_mut(&mut self) -> &mut T {
                 // We know statically that there are no other references to `self`, so
                 // there's no need to lock the inner mutex.
-                unsafe { &mut *self.value.get() }
-            }
-
-            fn remove_waker(&self, wait_key: usize, wake_another: bool) {
-                if wait_key != WAIT_KEY_NONE {
-                    let mut waiters = self.waiters.lock();
-                    match waiters.remove(wait_key) {
-                        Waiter::Waiting(_) => {}
-                        Waiter::Woken => {
-                            // We were awoken, but then dropped before we could
-                            // wake up to acquire the lock. Wake up another
-                            // waiter.
-                            if wake_another {
-                                if let Some((_i, waiter)) = waiters.iter_mut().next() {
-                                    waiter.wake();
-                                }
-                            }
-                        }
+                self.value.with_mut(|value| unsafe { &mut *value })
+            }
+
+            /// Get a snapshot of the contention counters of this mutex.
+            #[cfg(feature = "metrics")]
+            pub fn stats(&self) -> crate::metrics::LockStats {
+                self.metrics.stats()
+            }
+
+            fn try_acquire(&self) -> bool {
+                self.raw.try_acquire()
+            }
+
+            // Remove the node of a lock future from the queue, if it's in there.
+            fn remove_waker(&self, node: &mut Option<Pin<Box<Node>>>, wake_another: bool) {
+                if let Some(node) = node.take()
+                    && let Some(waker) = unsafe { self.raw.dequeue(&node, wake_another) }
+                {
+                    waker.wake();
+                }
+            }
+
+            // Attempt to acquire the lock for a lock future, queueing its node
+            // if it's held. The node is allocated on the first wait, and is
+            // queued as long as it's there.
+            fn poll_acquire(
+                &self,
+                node: &mut Option<Pin<Box<Node>>>,
+                priority: u32,
+                site: Site,
+                wait: &mut WaitStart,
+                cx: &mut Context<'_>,
+            ) -> Poll<()> {
+                let owner = Owner::thread();
+                self.id.wait(owner, site);
+
+                if !self.try_acquire() {
+                    let queued = node.is_some();
+                    if !queued {
+                        self.metrics.wait(wait);
                     }
-                    if waiters.is_empty() {
-                        self.state.fetch_and(!HAS_WAITERS, Ordering::Relaxed); // released by mutex unlock
+                    let queued_node = node.get_or_insert_with(|| Box::pin(Node::new()));
+                    // The lock may have been handed over by `bump`.
+                    let handed =
+                        unsafe { self.raw.enqueue(queued_node, queued, priority, cx.waker()) };
+                    if handed {
+                        *node = None;
+                    } else if !self.try_acquire() {
+                        // Ensure that we haven't raced `MutexGuard::drop`'s unlock path by
+                        // attempting to acquire the lock again.
+                        return Poll::Pending;
                     }
                 }
+
+                self.remove_waker(node, false);
+                self.id.acquire(owner, site);
+                self.metrics.acquire(wait);
+                Poll::Ready(())
             }
 
             // Unlocks the mutex. Called by `MutexGuard` and `MappedMutexGuard` when they
             // are dropped.
             fn unlock(&self) {
-                let old_state = self.state.fetch_and(!IS_LOCKED, Ordering::AcqRel);
-                if (old_state & HAS_WAITERS) != 0 {
-                    let mut waiters = self.waiters.lock();
-                    if let Some((_i, waiter)) = waiters.iter_mut().next() {
-                        waiter.wake();
+                self.id.release();
+                if let Some(waker) = self.raw.release() {
+                    waker.wake();
+                }
+            }
+
+            // Release the lock held by a guard while `f` runs, then wait for
+            // `relock`. `locked` stays cleared if this is cancelled in between.
+            async fn unlocked<F, Fut>(
+                &self,
+                locked: &mut bool,
+                relock: MutexLockFuture<'_, T>,
+                f: F,
+            ) -> Fut::Output
+            where
+                F: FnOnce() -> Fut,
+                Fut: Future,
+            {
+                *locked = false;
+                self.unlock();
+                let output = f().await;
+                // The new guard is merged into the one that called us.
+                mem::forget(relock.await);
+                *locked = true;
+                output
+            }
+
+            // Hand the lock over to the next waiter, then wait for `relock`
+            // behind the others. Without a handoff, `relock` would take the
+            // lock back before the woken waiter gets to run.
+            async fn bump(&self, locked: &mut bool, relock: MutexLockFuture<'_, T>) {
+                if self.raw.has_waiters() {
+                    *locked = false;
+                    self.id.release();
+                    if let Some(waker) = self.raw.hand_over() {
+                        waker.wake();
                     }
+                    // The new guard is merged into the one that called us.
+                    mem::forget(relock.await);
+                    *locked = true;
                 }
             }
         }
 
+        impl<'a, T: ?Sized> sealed::Lockable for &'a Mutex<T> {
+            type Guard = MutexGuard<'a, T>;
+
+            fn addr(&self) -> usize {
+                (*self as *const Mutex<T>).addr()
+            }
+
+            #[track_caller]
+            fn lock(self) -> impl Future<Output = Self::Guard> {
+                Mutex::lock(self)
+            }
+
+            #[track_caller]
+            fn try_lock(self) -> Option<Self::Guard> {
+                Mutex::try_lock(self)
+            }
+        }
+
+        #[cfg(not(loom))]
+        impl<T: ?Sized> sealed::Lockable for Shared<Mutex<T>> {
+            type Guard = OwnedMutexGuard<T>;
+
+            fn addr(&self) -> usize {
+                Shared::as_ptr(self).addr()
+            }
+
+            #[track_caller]
+            fn lock(self) -> impl Future<Output = Self::Guard> {
+                Mutex::lock_owned(self)
+            }
+
+            #[track_caller]
+            fn try_lock(self) -> Option<Self::Guard> {
+                Mutex::try_lock_owned(&self)
+            }
+        }
+
         /// A future which resolves when the target mutex has been successfully
         /// acquired, owned version.
         pub struct OwnedMutexLockFuture<T: ?Sized> {
             // `None` indicates that the mutex was successfully acquired.
             mutex: Option<Shared<Mutex<T>>>,
-            wait_key: usize,
+            // The node of the future while it's queued.
+            node: Option<Pin<Box<Node>>>,
+            priority: u32,
+            site: Site,
+            wait: WaitStart,
         }
 
         impl<T: ?Sized> fmt::Debug for OwnedMutexLockFuture<T> {
@@ -260,14 +1128,7 @@ macro_rules! impl_mutex {
                 f.debug_struct("OwnedMutexLockFuture")
                     .field("was_acquired", &self.mutex.is_none())
                     .field("mutex", &self.mutex)
-                    .field(
-                        "wait_key",
-                        &(if self.wait_key == WAIT_KEY_NONE {
-                            None
-                        } else {
-                            Some(self.wait_key)
-                        }),
-                    )
+                    .field("queued", &self.node.is_some())
                     .finish()
             }
         }
@@ -282,34 +1143,17 @@ macro_rules! impl_mutex {
                     .mutex
                     .as_ref()
                     .expect("polled OwnedMutexLockFuture after completion");
-
-                if let Some(lock) = mutex.try_lock_owned() {
-                    mutex.remove_waker(this.wait_key, false);
-                    this.mutex = None;
-                    return Poll::Ready(lock);
-                }
-
-                {
-                    let mut waiters = mutex.waiters.lock();
-                    if this.wait_key == WAIT_KEY_NONE {
-                        this.wait_key = waiters.insert(Waiter::Waiting(cx.waker().clone()));
-                        if waiters.len() == 1 {
-                            mutex.state.fetch_or(HAS_WAITERS, Ordering::Relaxed); // released by mutex unlock
-                        }
-                    } else {
-                        waiters[this.wait_key].register(cx.waker());
-                    }
-                }
-
-                // Ensure that we haven't raced `MutexGuard::drop`'s unlock path by
-                // attempting to acquire the lock again.
-                if let Some(lock) = mutex.try_lock_owned() {
-                    mutex.remove_waker(this.wait_key, false);
-                    this.mutex = None;
-                    return Poll::Ready(lock);
-                }
-
-                Poll::Pending
+                let poll = mutex.poll_acquire(
+                    &mut this.node,
+                    this.priority,
+                    this.site,
+                    &mut this.wait,
+                    cx,
+                );
+                poll.map(|()| OwnedMutexGuard {
+                    mutex: this.mutex.take().unwrap(),
+                    locked: true,
+                })
             }
         }
 
@@ -318,9 +1162,10 @@ macro_rules! impl_mutex {
                 if let Some(mutex) = self.mutex.as_ref() {
                     // This future was dropped before it acquired the mutex.
                     //
-                    // Remove ourselves from the map, waking up another waiter if we
+                    // Remove ourselves from the queue, waking up another waiter if we
                     // had been awoken to acquire the lock.
-                    mutex.remove_waker(self.wait_key, true);
+                    mutex.remove_waker(&mut self.node, true);
+                    mutex.metrics.cancel(&mut self.wait);
                 }
             }
         }
@@ -328,8 +1173,43 @@ macro_rules! impl_mutex {
         /// An RAII guard returned by the `lock_owned` and `try_lock_owned` methods.
         /// When this structure is dropped (falls out of scope), the lock will be
         /// unlocked.
+        ///
+        /// # Panics
+        ///
+        /// Accessing the value panics if a call to `unlocked` or `bump` was
+        /// cancelled, since the guard no longer holds the lock.
         pub struct OwnedMutexGuard<T: ?Sized> {
             mutex: Shared<Mutex<T>>,
+            // Cleared while `unlocked` runs, and left so if it is cancelled.
+            locked: bool,
+        }
+
+        impl<T: ?Sized> OwnedMutexGuard<T> {
+            /// Release the lock while the future returned by `f` runs, and
+            /// acquire it again before returning its output.
+            ///
+            /// See [`MutexGuard::unlocked`] for detail.
+            #[track_caller]
+            pub fn unlocked<F, Fut>(this: &mut Self, f: F) -> impl Future<Output = Fut::Output>
+            where
+                F: FnOnce() -> Fut,
+                Fut: Future,
+            {
+                assert_locked(this.locked);
+                let relock = this.mutex.lock();
+                this.mutex.unlocked(&mut this.locked, relock, f)
+            }
+
+            /// Hand the lock over to the next task waiting for it, if any, and
+            /// acquire it again behind the other waiting tasks.
+            ///
+            /// See [`MutexGuard::bump`] for detail.
+            #[track_caller]
+            pub fn bump(this: &mut Self) -> impl Future<Output = ()> {
+                assert_locked(this.locked);
+                let relock = this.mutex.lock();
+                this.mutex.bump(&mut this.locked, relock)
+            }
         }
 
         impl<T: ?Sized + fmt::Debug> fmt::Debug for OwnedMutexGuard<T> {
@@ -343,7 +1223,9 @@ macro_rules! impl_mutex {
 
         impl<T: ?Sized> Drop for OwnedMutexGuard<T> {
             fn drop(&mut self) {
-                self.mutex.unlock()
+                if self.locked {
+                    self.mutex.unlock()
+                }
             }
         }
 
@@ -351,13 +1233,15 @@ macro_rules! impl_mutex {
             type Target = T;
 
             fn deref(&self) -> &T {
-                unsafe { &*self.mutex.value.get() }
+                assert_locked(self.locked);
+                self.mutex.value.with(|value| unsafe { &*value })
             }
         }
 
         impl<T: ?Sized> DerefMut for OwnedMutexGuard<T> {
             fn deref_mut(&mut self) -> &mut T {
-                unsafe { &mut *self.mutex.value.get() }
+                assert_locked(self.locked);
+                self.mutex.value.with_mut(|value| unsafe { &mut *value })
             }
         }
 
@@ -366,7 +1250,11 @@ macro_rules! impl_mutex {
         pub struct MutexLockFuture<'a, T: ?Sized> {
             // `None` indicates that the mutex was successfully acquired.
             mutex: Option<&'a Mutex<T>>,
-            wait_key: usize,
+            // The node of the future while it's queued.
+            node: Option<Pin<Box<Node>>>,
+            priority: u32,
+            site: Site,
+            wait: WaitStart,
         }
 
         impl<T: ?Sized> fmt::Debug for MutexLockFuture<'_, T> {
@@ -374,14 +1262,7 @@ macro_rules! impl_mutex {
                 f.debug_struct("MutexLockFuture")
                     .field("was_acquired", &self.mutex.is_none())
                     .field("mutex", &self.mutex)
-                    .field(
-                        "wait_key",
-                        &(if self.wait_key == WAIT_KEY_NONE {
-                            None
-                        } else {
-                            Some(self.wait_key)
-                        }),
-                    )
+                    .field("queued", &self.node.is_some())
                     .finish()
             }
         }
@@ -389,36 +1270,24 @@ macro_rules! impl_mutex {
         impl<'a, T: ?Sized> Future for MutexLockFuture<'a, T> {
             type Output = MutexGuard<'a, T>;
 
-            fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-                let mutex = self.mutex.expect("polled MutexLockFuture after completion");
-
-                if let Some(lock) = mutex.try_lock() {
-                    mutex.remove_waker(self.wait_key, false);
-                    self.mutex = None;
-                    return Poll::Ready(lock);
-                }
+            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+                let this = self.get_mut();
 
-                {
-                    let mut waiters = mutex.waiters.lock();
-                    if self.wait_key == WAIT_KEY_NONE {
-                        self.wait_key = waiters.insert(Waiter::Waiting(cx.waker().clone()));
-                        if waiters.len() == 1 {
-                            mutex.state.fetch_or(HAS_WAITERS, Ordering::Relaxed); // released by mutex unlock
-                        }
-                    } else {
-                        waiters[self.wait_key].register(cx.waker());
+                let mutex = this.mutex.expect("polled MutexLockFuture after completion");
+                let poll = mutex.poll_acquire(
+                    &mut this.node,
+                    this.priority,
+                    this.site,
+                    &mut this.wait,
+                    cx,
+                );
+                poll.map(|()| {
+                    this.mutex = None;
+                    MutexGuard {
+                        mutex,
+                        locked: true,
                     }
-                }
-
-                // Ensure that we haven't raced `MutexGuard::drop`'s unlock path by
-                // attempting to acquire the lock again.
-                if let Some(lock) = mutex.try_lock() {
-                    mutex.remove_waker(self.wait_key, false);
-                    self.mutex = None;
-                    return Poll::Ready(lock);
-                }
-
-                Poll::Pending
+                })
             }
         }
 
@@ -427,9 +1296,10 @@ macro_rules! impl_mutex {
                 if let Some(mutex) = self.mutex {
                     // This future was dropped before it acquired the mutex.
                     //
-                    // Remove ourselves from the map, waking up another waiter if we
+                    // Remove ourselves from the queue, waking up another waiter if we
                     // had been awoken to acquire the lock.
-                    mutex.remove_waker(self.wait_key, true);
+                    mutex.remove_waker(&mut self.node, true);
+                    mutex.metrics.cancel(&mut self.wait);
                 }
             }
         }
@@ -437,11 +1307,97 @@ macro_rules! impl_mutex {
         /// An RAII guard returned by the `lock` and `try_lock` methods.
         /// When this structure is dropped (falls out of scope), the lock will be
         /// unlocked.
+        ///
+        /// # Panics
+        ///
+        /// Accessing the value panics if a call to `unlocked` or `bump` was
+        /// cancelled, since the guard no longer holds the lock.
         pub struct MutexGuard<'a, T: ?Sized> {
             mutex: &'a Mutex<T>,
+            // Cleared while `unlocked` runs, and left so if it is cancelled.
+            locked: bool,
         }
 
         impl<'a, T: ?Sized> MutexGuard<'a, T> {
+            /// Release the lock while the future returned by `f` runs, and
+            /// acquire it again before returning its output.
+            ///
+            /// The guard can't be used in the meantime, and other tasks may
+            /// modify the value: don't assume it is unchanged.
+            ///
+            /// # Cancellation
+            ///
+            /// If the returned future is dropped before completing, the lock
+            /// is left released, and any further access through the guard
+            /// panics.
+            ///
+            /// # Panics
+            ///
+            /// Panics if a previous call was cancelled.
+            ///
+            /// # Example
+            ///
+            /// ```
+            /// # futures::executor::block_on(async {
+            #[doc = concat!("use synchrony::", stringify!($sync), "::mutex::{Mutex, MutexGuard};")]
+            ///
+            /// let mutex = Mutex::new(0);
+            /// let mut guard = mutex.lock().await;
+            /// let value = MutexGuard::unlocked(&mut guard, || async {
+            ///     // The lock is free while waiting for the I/O.
+            ///     *mutex.try_lock().unwrap() += 1;
+            ///     42
+            /// })
+            /// .await;
+            /// *guard += value;
+            /// assert_eq!(*guard, 43);
+            /// # });
+            /// ```
+            #[track_caller]
+            pub fn unlocked<F, Fut>(this: &mut Self, f: F) -> impl Future<Output = Fut::Output>
+            where
+                F: FnOnce() -> Fut,
+                Fut: Future,
+            {
+                assert_locked(this.locked);
+                this.mutex
+                    .unlocked(&mut this.locked, this.mutex.lock(), f)
+            }
+
+            /// Hand the lock over to the next task waiting for it, if any, and
+            /// acquire it again behind the other waiting tasks.
+            ///
+            /// This is useful to avoid starving other tasks while holding the
+            /// lock for a long time. It does nothing if no task is waiting.
+            ///
+            /// # Example
+            ///
+            /// ```
+            /// # futures::executor::block_on(async {
+            #[doc = concat!("use synchrony::", stringify!($sync), "::mutex::{Mutex, MutexGuard};")]
+            ///
+            /// let mutex = Mutex::new(Vec::new());
+            /// let mut guard = mutex.lock().await;
+            /// futures::join!(
+            ///     async { mutex.lock().await.push("waiter") },
+            ///     async {
+            ///         MutexGuard::bump(&mut guard).await;
+            ///         guard.push("bumped");
+            ///     },
+            /// );
+            /// assert_eq!(*guard, ["waiter", "bumped"]);
+            /// # });
+            /// ```
+            ///
+            /// # Cancellation
+            ///
+            /// See [`unlocked`](Self::unlocked).
+            #[track_caller]
+            pub fn bump(this: &mut Self) -> impl Future<Output = ()> {
+                assert_locked(this.locked);
+                this.mutex.bump(&mut this.locked, this.mutex.lock())
+            }
+
             /// Returns a locked view over a portion of the locked data.
             ///
             /// # Example
@@ -462,14 +1418,16 @@ macro_rules! impl_mutex {
             where
                 F: FnOnce(&mut T) -> &mut U,
             {
+                assert_locked(this.locked);
                 let mutex = this.mutex;
-                let value = f(unsafe { &mut *this.mutex.value.get() });
+                let value = f(this.mutex.value.with_mut(|value| unsafe { &mut *value }));
                 // Don't run the `drop` method for MutexGuard. The ownership of the underlying
                 // locked state is being moved to the returned MappedMutexGuard.
                 mem::forget(this);
                 MappedMutexGuard {
                     mutex,
                     value,
+                    locked: true,
                     _marker: PhantomData,
                 }
             }
@@ -486,7 +1444,9 @@ macro_rules! impl_mutex {
 
         impl<T: ?Sized> Drop for MutexGuard<'_, T> {
             fn drop(&mut self) {
-                self.mutex.unlock()
+                if self.locked {
+                    self.mutex.unlock()
+                }
             }
         }
 
@@ -494,26 +1454,70 @@ macro_rules! impl_mutex {
             type Target = T;
 
             fn deref(&self) -> &T {
-                unsafe { &*self.mutex.value.get() }
+                assert_locked(self.locked);
+                self.mutex.value.with(|value| unsafe { &*value })
             }
         }
 
         impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
             fn deref_mut(&mut self) -> &mut T {
-                unsafe { &mut *self.mutex.value.get() }
+                assert_locked(self.locked);
+                self.mutex.value.with_mut(|value| unsafe { &mut *value })
             }
         }
 
         /// An RAII guard returned by the `MutexGuard::map` and `MappedMutexGuard::map`
         /// methods. When this structure is dropped (falls out of scope), the lock will
         /// be unlocked.
+        ///
+        /// # Panics
+        ///
+        /// Accessing the value panics if a call to `unlocked` was cancelled,
+        /// since the guard no longer holds the lock.
         pub struct MappedMutexGuard<'a, T: ?Sized, U: ?Sized> {
             mutex: &'a Mutex<T>,
             value: *mut U,
+            // Cleared while `unlocked` runs, and left so if it is cancelled.
+            locked: bool,
             _marker: PhantomData<&'a mut U>,
         }
 
         impl<'a, T: ?Sized, U: ?Sized> MappedMutexGuard<'a, T, U> {
+            /// Release the lock while the future returned by `f` runs, and
+            /// acquire it again before returning its output.
+            ///
+            /// The guard keeps pointing to the same portion of the data, which
+            /// other tasks may modify in the meantime. See
+            /// [`MutexGuard::unlocked`] for detail.
+            ///
+            /// # Example
+            ///
+            /// ```
+            /// # futures::executor::block_on(async {
+            #[doc = concat!("use synchrony::", stringify!($sync), "::mutex::{MappedMutexGuard, Mutex, MutexGuard};")]
+            ///
+            /// let mutex = Mutex::new((0, 0));
+            /// let mut first = MutexGuard::map(mutex.lock().await, |(first, _)| first);
+            /// MappedMutexGuard::unlocked(&mut first, || async {
+            ///     *mutex.try_lock().unwrap() = (1, 2);
+            /// })
+            /// .await;
+            /// *first += 10;
+            /// drop(first);
+            /// assert_eq!(*mutex.try_lock().unwrap(), (11, 2));
+            /// # });
+            /// ```
+            #[track_caller]
+            pub fn unlocked<F, Fut>(this: &mut Self, f: F) -> impl Future<Output = Fut::Output>
+            where
+                F: FnOnce() -> Fut,
+                Fut: Future,
+            {
+                assert_locked(this.locked);
+                this.mutex
+                    .unlocked(&mut this.locked, this.mutex.lock(), f)
+            }
+
             /// Returns a locked view over a portion of the locked data.
             ///
             /// # Example
@@ -535,6 +1539,7 @@ macro_rules! impl_mutex {
             where
                 F: FnOnce(&mut U) -> &mut V,
             {
+                assert_locked(this.locked);
                 let mutex = this.mutex;
                 let value = f(unsafe { &mut *this.value });
                 // Don't run the `drop` method for MappedMutexGuard. The ownership of the
@@ -544,6 +1549,7 @@ macro_rules! impl_mutex {
                 MappedMutexGuard {
                     mutex,
                     value,
+                    locked: true,
                     _marker: PhantomData,
                 }
             }
@@ -560,7 +1566,9 @@ macro_rules! impl_mutex {
 
         impl<T: ?Sized, U: ?Sized> Drop for MappedMutexGuard<'_, T, U> {
             fn drop(&mut self) {
-                self.mutex.unlock()
+                if self.locked {
+                    self.mutex.unlock()
+                }
             }
         }
 
@@ -568,12 +1576,14 @@ macro_rules! impl_mutex {
             type Target = U;
 
             fn deref(&self) -> &U {
+                assert_locked(self.locked);
                 unsafe { &*self.value }
             }
         }
 
         impl<T: ?Sized, U: ?Sized> DerefMut for MappedMutexGuard<'_, T, U> {
             fn deref_mut(&mut self) -> &mut U {
+                assert_locked(self.locked);
                 unsafe { &mut *self.value }
             }
         }
@@ -2,20 +2,53 @@
 
 /// Multithreaded blocking Mutex
 pub mod sync {
-    crate::cfg_loom! {
-        use std::sync::{Mutex as Inner, MutexGuard as InnerGuard};
-    }
-    use std::{
+    use core::{
         fmt,
         ops::{Deref, DerefMut},
     };
 
+    #[cfg(not(feature = "std"))]
+    use crate::sync::spin::{SpinMutex as Inner, SpinMutexGuard as InnerGuard};
+    use crate::{
+        deadlock::{Held, LockId, Owner, Site},
+        metrics::{Metrics, WaitStart},
+    };
+
+    #[cfg(feature = "std")]
+    crate::cfg_loom! {
+        use std::sync::{Mutex as Inner, MutexGuard as InnerGuard};
+    }
+
     /// A multithreaded Mutex based on [`std::sync::Mutex`].
-    pub struct Mutex<T: ?Sized>(Inner<T>);
+    ///
+    /// Without the `std` feature, this is based on a
+    /// [`SpinMutex`](crate::sync::spin::SpinMutex) instead.
+    ///
+    /// [`std::sync::Mutex`]: https://doc.rust-lang.org/std/sync/struct.Mutex.html
+    pub struct Mutex<T: ?Sized> {
+        id: LockId,
+        metrics: Metrics,
+        inner: Inner<T>,
+    }
 
     impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            self.0.fmt(f)
+            self.inner.fmt(f)
+        }
+    }
+
+    /// Serializes the value by locking the mutex.
+    #[cfg(feature = "serde")]
+    impl<T: ?Sized + serde::Serialize> serde::Serialize for Mutex<T> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.lock().1.serialize(serializer)
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Mutex<T> {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            T::deserialize(deserializer).map(Self::new)
         }
     }
 
@@ -23,7 +56,11 @@ pub mod sync {
         /// Creates a new mutex in an unlocked state ready for use.
         #[cfg(not(loom))]
         pub const fn new(val: T) -> Self {
-            Self(Inner::new(val))
+            Self {
+                id: LockId::new(),
+                metrics: Metrics::new(),
+                inner: Inner::new(val),
+            }
         }
 
         /// Creates a new mutex in an unlocked state ready for use.
@@ -31,12 +68,21 @@ pub mod sync {
         /// This `new` is not `const` due to loom not supporting it.
         #[cfg(loom)]
         pub fn new(val: T) -> Self {
-            Self(Inner::new(val))
+            Self {
+                id: LockId::new(),
+                metrics: Metrics::new(),
+                inner: Inner::new(val),
+            }
         }
 
         /// Get the inner [`std::sync::Mutex`].
+        ///
+        /// Requires the `std` feature, as it's based on a spinlock otherwise.
+        ///
+        /// [`std::sync::Mutex`]: https://doc.rust-lang.org/std/sync/struct.Mutex.html
+        #[cfg(feature = "std")]
         pub fn into_inner(self) -> Inner<T> {
-            self.0
+            self.inner
         }
     }
 
@@ -46,31 +92,65 @@ pub mod sync {
         ///
         /// See [`std::sync::Mutex::lock`] for detail.
         ///
+        /// [`std::sync::Mutex::lock`]: https://doc.rust-lang.org/std/sync/struct.Mutex.html#method.lock
+        ///
         /// # Panics
         ///
         /// This function might panic when called if the lock is already held by
         /// the current thread or is poisoned (some thread panicked while
-        /// holding the lock).
+        /// holding the lock). Without the `std` feature, locking twice on the
+        /// same thread spins forever instead.
+        #[track_caller]
         pub fn lock(&self) -> MutexGuard<'_, T> {
-            MutexGuard(self.0.lock().unwrap())
+            let (owner, site) = (Owner::thread(), Site::caller());
+            self.id.wait(owner, site);
+            let mut wait = WaitStart::new();
+            #[cfg(feature = "metrics")]
+            if let Ok(guard) = self.inner.try_lock() {
+                self.metrics.acquire(&mut wait);
+                return MutexGuard(self.id.hold(owner, site), guard);
+            }
+            // `try_lock` also fails when poisoned, in which case `lock` panics
+            // below.
+            self.metrics.wait(&mut wait);
+            #[cfg(feature = "std")]
+            let guard = self.inner.lock().unwrap();
+            #[cfg(not(feature = "std"))]
+            let guard = self.inner.lock();
+            self.metrics.acquire(&mut wait);
+            MutexGuard(self.id.hold(owner, site), guard)
+        }
+
+        /// Get a snapshot of the contention counters of this mutex.
+        #[cfg(feature = "metrics")]
+        pub fn stats(&self) -> crate::metrics::LockStats {
+            self.metrics.stats()
         }
     }
 
     /// An RAII implementation of a "scoped lock" of a mutex. When this
     /// structure is dropped (falls out of scope), the lock will be
     /// unlocked.
-    pub struct MutexGuard<'a, T: ?Sized>(InnerGuard<'a, T>);
+    //
+    // `Held` comes first so that it's dropped first: the lock must still be held
+    // when its holder is unregistered, or it could erase the next holder's.
+    pub struct MutexGuard<'a, T: ?Sized>(#[allow(dead_code)] Held<'a>, InnerGuard<'a, T>);
 
     impl<T: ?Sized + fmt::Debug> fmt::Debug for MutexGuard<'_, T> {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            self.0.fmt(f)
+            self.1.fmt(f)
         }
     }
 
     impl<'a, T: ?Sized> MutexGuard<'a, T> {
         /// Get the inner [`std::sync::MutexGuard`].
+        ///
+        /// Requires the `std` feature, as it's based on a spinlock otherwise.
+        ///
+        /// [`std::sync::MutexGuard`]: https://doc.rust-lang.org/std/sync/struct.MutexGuard.html
+        #[cfg(feature = "std")]
         pub fn into_inner(self) -> InnerGuard<'a, T> {
-            self.0
+            self.1
         }
     }
 
@@ -78,76 +158,363 @@ pub mod sync {
         type Target = T;
 
         fn deref(&self) -> &Self::Target {
-            &self.0
+            &self.1
         }
     }
 
     impl<'a, T> DerefMut for MutexGuard<'a, T> {
         fn deref_mut(&mut self) -> &mut Self::Target {
-            &mut self.0
+            &mut self.1
         }
     }
 
     impl<T: Send> crate::AssertMt for Mutex<T> {}
+
+    /// The lock behind [`Mutex`], without deadlock detection nor metrics, for
+    /// the internal locks of other primitives. Only the locks users take
+    /// themselves are recorded.
+    pub(crate) struct RawMutex<T>(Inner<T>);
+
+    pub(crate) type RawMutexGuard<'a, T> = InnerGuard<'a, T>;
+
+    impl<T> RawMutex<T> {
+        #[cfg(not(loom))]
+        pub(crate) const fn new(val: T) -> Self {
+            Self(Inner::new(val))
+        }
+
+        #[cfg(loom)]
+        pub(crate) fn new(val: T) -> Self {
+            Self(Inner::new(val))
+        }
+
+        pub(crate) fn lock(&self) -> RawMutexGuard<'_, T> {
+            #[cfg(feature = "std")]
+            let guard = self.0.lock().unwrap();
+            #[cfg(not(feature = "std"))]
+            let guard = self.0.lock();
+            guard
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub use reentrant::{ReentrantMutex, ReentrantMutexGuard};
+
+    #[cfg(feature = "std")]
+    mod reentrant {
+        use core::{
+            cell::UnsafeCell, fmt, marker::PhantomData, mem, ops::Deref, sync::atomic::Ordering,
+        };
+
+        crate::cfg_loom! {
+            use std::thread_local;
+        }
+
+        use super::{Mutex, MutexGuard};
+        use crate::sync::atomic::AtomicUsize;
+
+        thread_local! {
+            // loom doesn't support `const` initializers.
+            #[allow(clippy::missing_const_for_thread_local)]
+            static THREAD: u8 = 0;
+        }
+
+        // A nonzero identifier of the current thread, unique among live threads.
+        fn current_thread() -> usize {
+            THREAD.with(|thread| thread as *const u8 as usize)
+        }
+
+        /// A reentrant Mutex, which can be locked again by the thread that
+        /// holds it, like `parking_lot::ReentrantMutex`.
+        ///
+        /// Since a thread may hold several guards at once, they only give out
+        /// shared references. Use a [`Cell`] or [`RefCell`] for mutability.
+        ///
+        /// [`Cell`]: core::cell::Cell
+        /// [`RefCell`]: core::cell::RefCell
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use std::cell::Cell;
+        ///
+        /// use synchrony::sync::mutex_blocking::ReentrantMutex;
+        ///
+        /// let mutex = ReentrantMutex::new(Cell::new(0));
+        /// let outer = mutex.lock();
+        /// let inner = mutex.lock();
+        /// inner.set(inner.get() + 1);
+        /// assert_eq!(outer.get(), 1);
+        /// ```
+        pub struct ReentrantMutex<T: ?Sized> {
+            mutex: Mutex<()>,
+            // The guard of `mutex` while it's held, only accessed by `owner`.
+            guard: UnsafeCell<Option<MutexGuard<'static, ()>>>,
+            owner: AtomicUsize,
+            count: AtomicUsize,
+            value: T,
+        }
+
+        unsafe impl<T: ?Sized + Send> Send for ReentrantMutex<T> {}
+        // The value is only ever shared with the thread holding the lock.
+        unsafe impl<T: ?Sized + Send> Sync for ReentrantMutex<T> {}
+
+        impl<T: Send> crate::AssertMt for ReentrantMutex<T> {}
+
+        impl<T> ReentrantMutex<T> {
+            /// Creates a new reentrant mutex in an unlocked state ready for
+            /// use.
+            #[cfg(not(loom))]
+            pub const fn new(val: T) -> Self {
+                Self {
+                    mutex: Mutex::new(()),
+                    guard: UnsafeCell::new(None),
+                    owner: AtomicUsize::new(0),
+                    count: AtomicUsize::new(0),
+                    value: val,
+                }
+            }
+
+            /// Creates a new reentrant mutex in an unlocked state ready for
+            /// use.
+            ///
+            /// This `new` is not `const` due to loom not supporting it.
+            #[cfg(loom)]
+            pub fn new(val: T) -> Self {
+                Self {
+                    mutex: Mutex::new(()),
+                    guard: UnsafeCell::new(None),
+                    owner: AtomicUsize::new(0),
+                    count: AtomicUsize::new(0),
+                    value: val,
+                }
+            }
+
+            /// Consumes this mutex, returning the underlying data.
+            pub fn into_inner(self) -> T {
+                self.value
+            }
+        }
+
+        impl<T: ?Sized> ReentrantMutex<T> {
+            /// Acquires the mutex, blocking the current thread until it is able
+            /// to do so, unless it already holds it.
+            ///
+            /// # Panics
+            ///
+            /// This function might panic if the lock is poisoned, or if it is
+            /// locked more than `usize::MAX` times.
+            #[track_caller]
+            pub fn lock(&self) -> ReentrantMutexGuard<'_, T> {
+                let thread = current_thread();
+                if self.owner.load(Ordering::Relaxed) == thread {
+                    let count = self.count.load(Ordering::Relaxed);
+                    self.count.store(
+                        count.checked_add(1).expect("lock count overflow"),
+                        Ordering::Relaxed,
+                    );
+                } else {
+                    let guard = self.mutex.lock();
+                    // SAFETY: the guard is dropped by the last `ReentrantMutexGuard`,
+                    // which borrows `self`.
+                    let guard = unsafe {
+                        mem::transmute::<MutexGuard<'_, ()>, MutexGuard<'static, ()>>(guard)
+                    };
+                    // SAFETY: we hold the lock.
+                    unsafe { *self.guard.get() = Some(guard) };
+                    self.owner.store(thread, Ordering::Relaxed);
+                    self.count.store(1, Ordering::Relaxed);
+                }
+                ReentrantMutexGuard {
+                    mutex: self,
+                    _marker: PhantomData,
+                }
+            }
+
+            /// Returns whether the mutex is held by any thread.
+            pub fn is_locked(&self) -> bool {
+                self.owner.load(Ordering::Relaxed) != 0
+            }
+
+            /// Returns a mutable reference to the underlying data.
+            pub fn get_mut(&mut self) -> &mut T {
+                &mut self.value
+            }
+
+            /// Get a snapshot of the contention counters of this mutex.
+            ///
+            /// Reentrant acquisitions are not counted.
+            #[cfg(feature = "metrics")]
+            pub fn stats(&self) -> crate::metrics::LockStats {
+                self.mutex.stats()
+            }
+
+            fn unlock(&self) {
+                let count = self.count.load(Ordering::Relaxed) - 1;
+                self.count.store(count, Ordering::Relaxed);
+                if count == 0 {
+                    self.owner.store(0, Ordering::Relaxed);
+                    // SAFETY: we still hold the lock.
+                    drop(unsafe { (*self.guard.get()).take() });
+                }
+            }
+        }
+
+        impl<T: ?Sized + fmt::Debug> fmt::Debug for ReentrantMutex<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("ReentrantMutex")
+                    .field("is_locked", &self.is_locked())
+                    .finish_non_exhaustive()
+            }
+        }
+
+        impl<T: Default> Default for ReentrantMutex<T> {
+            fn default() -> Self {
+                Self::new(T::default())
+            }
+        }
+
+        impl<T> From<T> for ReentrantMutex<T> {
+            fn from(val: T) -> Self {
+                Self::new(val)
+            }
+        }
+
+        /// An RAII implementation of a "scoped lock" of a reentrant mutex. The
+        /// mutex is unlocked when the last guard of the holding thread is
+        /// dropped.
+        pub struct ReentrantMutexGuard<'a, T: ?Sized> {
+            mutex: &'a ReentrantMutex<T>,
+            // Must be dropped on the thread that locked the mutex.
+            _marker: PhantomData<*const ()>,
+        }
+
+        unsafe impl<T: ?Sized + Sync> Sync for ReentrantMutexGuard<'_, T> {}
+
+        impl<T: ?Sized> Deref for ReentrantMutexGuard<'_, T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                &self.mutex.value
+            }
+        }
+
+        impl<T: ?Sized> Drop for ReentrantMutexGuard<'_, T> {
+            fn drop(&mut self) {
+                self.mutex.unlock();
+            }
+        }
+
+        impl<T: ?Sized + fmt::Debug> fmt::Debug for ReentrantMutexGuard<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                (**self).fmt(f)
+            }
+        }
+    }
 }
 
 /// Singlethreaded blocking Mutex
 pub mod unsync {
-    use std::{
-        cell::{RefCell as Inner, RefMut as InnerGuard},
+    use core::{
+        cell::{Cell, RefCell as Inner, RefMut as InnerGuard},
         fmt,
         ops::{Deref, DerefMut},
     };
 
-    /// A singlethreaded Mutex based on [`std::cell::RefCell`].
-    pub struct Mutex<T: ?Sized>(Inner<T>);
+    use crate::{
+        deadlock::{Held, LockId, Owner, Site},
+        metrics::{Metrics, WaitStart},
+    };
+
+    /// A singlethreaded Mutex based on [`core::cell::RefCell`].
+    pub struct Mutex<T: ?Sized> {
+        id: LockId,
+        metrics: Metrics,
+        inner: Inner<T>,
+    }
 
     impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            self.0.fmt(f)
+            self.inner.fmt(f)
+        }
+    }
+
+    /// Serializes the value by locking the mutex.
+    #[cfg(feature = "serde")]
+    impl<T: ?Sized + serde::Serialize> serde::Serialize for Mutex<T> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.lock().1.serialize(serializer)
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Mutex<T> {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            T::deserialize(deserializer).map(Self::new)
         }
     }
 
     impl<T> Mutex<T> {
         /// Creates a new mutex in an unlocked state ready for use.
         pub const fn new(val: T) -> Self {
-            Self(Inner::new(val))
+            Self {
+                id: LockId::new(),
+                metrics: Metrics::new(),
+                inner: Inner::new(val),
+            }
         }
 
-        /// Get the inner [`std::cell::RefCell`].
+        /// Get the inner [`core::cell::RefCell`].
         pub fn into_inner(self) -> Inner<T> {
-            self.0
+            self.inner
         }
     }
 
     impl<T: ?Sized> Mutex<T> {
         /// Acquires a mutex.
         ///
-        /// See [`std::cell::RefCell::borrow_mut`] for detail.
+        /// See [`core::cell::RefCell::borrow_mut`] for detail.
         ///
         /// # Panics
         ///
         /// Panics if the value is currently borrowed.
+        #[track_caller]
         pub fn lock(&self) -> MutexGuard<'_, T> {
-            MutexGuard(self.0.borrow_mut())
+            let (owner, site) = (Owner::thread(), Site::caller());
+            self.id.wait(owner, site);
+            let guard = self.inner.borrow_mut();
+            self.metrics.acquire(&mut WaitStart::new());
+            MutexGuard(self.id.hold(owner, site), guard)
+        }
+
+        /// Get a snapshot of the contention counters of this mutex.
+        ///
+        /// Since locking a singlethreaded mutex never waits, only acquisitions
+        /// are counted.
+        #[cfg(feature = "metrics")]
+        pub fn stats(&self) -> crate::metrics::LockStats {
+            self.metrics.stats()
         }
     }
 
     /// An RAII implementation of a "scoped lock" of a mutex. When this
     /// structure is dropped (falls out of scope), the lock will be
     /// unlocked.
-    pub struct MutexGuard<'a, T: ?Sized>(InnerGuard<'a, T>);
+    //
+    // `Held` comes first so that it's dropped first: the lock must still be held
+    // when its holder is unregistered, or it could erase the next holder's.
+    pub struct MutexGuard<'a, T: ?Sized>(#[allow(dead_code)] Held<'a>, InnerGuard<'a, T>);
 
     impl<T: ?Sized + fmt::Debug> fmt::Debug for MutexGuard<'_, T> {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            self.0.fmt(f)
+            self.1.fmt(f)
         }
     }
 
     impl<'a, T: ?Sized> MutexGuard<'a, T> {
-        /// Get the inner [`std::cell::RefMut`].
+        /// Get the inner [`core::cell::RefMut`].
         pub fn into_inner(self) -> InnerGuard<'a, T> {
-            self.0
+            self.1
         }
     }
 
@@ -155,13 +522,144 @@ pub mod unsync {
         type Target = T;
 
         fn deref(&self) -> &Self::Target {
-            &self.0
+            &self.1
         }
     }
 
     impl<'a, T> DerefMut for MutexGuard<'a, T> {
         fn deref_mut(&mut self) -> &mut Self::Target {
-            &mut self.0
+            &mut self.1
+        }
+    }
+
+    /// The lock behind [`Mutex`], without deadlock detection nor metrics, for
+    /// the internal locks of other primitives. Only the locks users take
+    /// themselves are recorded.
+    #[allow(dead_code)]
+    pub(crate) struct RawMutex<T>(Inner<T>);
+
+    #[allow(dead_code)]
+    pub(crate) type RawMutexGuard<'a, T> = InnerGuard<'a, T>;
+
+    #[allow(dead_code)]
+    impl<T> RawMutex<T> {
+        pub(crate) const fn new(val: T) -> Self {
+            Self(Inner::new(val))
+        }
+
+        pub(crate) fn lock(&self) -> RawMutexGuard<'_, T> {
+            self.0.borrow_mut()
+        }
+    }
+
+    /// A reentrant Mutex, which can be locked any number of times at once.
+    ///
+    /// Since there can be several guards at once, they only give out shared
+    /// references. Use a [`Cell`] or [`RefCell`] for mutability.
+    ///
+    /// [`Cell`]: core::cell::Cell
+    /// [`RefCell`]: core::cell::RefCell
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    ///
+    /// use synchrony::unsync::mutex_blocking::ReentrantMutex;
+    ///
+    /// let mutex = ReentrantMutex::new(Cell::new(0));
+    /// let outer = mutex.lock();
+    /// let inner = mutex.lock();
+    /// inner.set(inner.get() + 1);
+    /// assert_eq!(outer.get(), 1);
+    /// ```
+    pub struct ReentrantMutex<T: ?Sized> {
+        depth: Cell<usize>,
+        value: T,
+    }
+
+    impl<T> ReentrantMutex<T> {
+        /// Creates a new reentrant mutex in an unlocked state ready for use.
+        pub const fn new(val: T) -> Self {
+            Self {
+                depth: Cell::new(0),
+                value: val,
+            }
+        }
+
+        /// Consumes this mutex, returning the underlying data.
+        pub fn into_inner(self) -> T {
+            self.value
+        }
+    }
+
+    impl<T: ?Sized> ReentrantMutex<T> {
+        /// Acquires the mutex.
+        ///
+        /// # Panics
+        ///
+        /// Panics if it is locked more than `usize::MAX` times.
+        pub fn lock(&self) -> ReentrantMutexGuard<'_, T> {
+            let depth = self.depth.get();
+            self.depth
+                .set(depth.checked_add(1).expect("lock count overflow"));
+            ReentrantMutexGuard { mutex: self }
+        }
+
+        /// Returns whether the mutex is held.
+        pub fn is_locked(&self) -> bool {
+            self.depth.get() != 0
+        }
+
+        /// Returns a mutable reference to the underlying data.
+        pub fn get_mut(&mut self) -> &mut T {
+            &mut self.value
+        }
+    }
+
+    impl<T: ?Sized + fmt::Debug> fmt::Debug for ReentrantMutex<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("ReentrantMutex")
+                .field("is_locked", &self.is_locked())
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl<T: Default> Default for ReentrantMutex<T> {
+        fn default() -> Self {
+            Self::new(T::default())
+        }
+    }
+
+    impl<T> From<T> for ReentrantMutex<T> {
+        fn from(val: T) -> Self {
+            Self::new(val)
+        }
+    }
+
+    /// An RAII implementation of a "scoped lock" of a reentrant mutex. The
+    /// mutex is unlocked when the last guard is dropped.
+    pub struct ReentrantMutexGuard<'a, T: ?Sized> {
+        mutex: &'a ReentrantMutex<T>,
+    }
+
+    impl<T: ?Sized> Deref for ReentrantMutexGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            &self.mutex.value
+        }
+    }
+
+    impl<T: ?Sized> Drop for ReentrantMutexGuard<'_, T> {
+        fn drop(&mut self) {
+            self.mutex.depth.set(self.mutex.depth.get() - 1);
+        }
+    }
+
+    impl<T: ?Sized + fmt::Debug> fmt::Debug for ReentrantMutexGuard<'_, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            (**self).fmt(f)
         }
     }
 }
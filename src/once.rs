@@ -0,0 +1,437 @@
+//! Cells that can be written to only once, and values lazily initialized on
+//! first access.
+
+/// Multithreaded [`OnceLock`](sync::OnceLock) and [`Lazy`](sync::Lazy)
+pub mod sync {
+    #[cfg(all(feature = "std", not(loom)))]
+    use park::{wait_running, wake_waiting};
+
+    // Threads waiting for an initializer are parked, as it may take a while.
+    // All the cells share one condition variable, as initializers rarely run
+    // concurrently, and only wake it up if a thread is waiting.
+    #[cfg(all(feature = "std", not(loom)))]
+    mod park {
+        use core::sync::atomic::Ordering;
+        use std::sync::{Condvar, Mutex, PoisonError};
+
+        use super::super::{QUEUED, RUNNING};
+        use crate::sync::atomic::AtomicU8;
+
+        static LOCK: Mutex<()> = Mutex::new(());
+        static PARKED: Condvar = Condvar::new();
+
+        // Another thread is running the initializer.
+        pub fn wait_running(state: &AtomicU8) {
+            let mut guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+            // Ask the initializer to wake us up, under the lock so that it
+            // can't be missed.
+            while let Ok(_) | Err(QUEUED) =
+                state.compare_exchange(RUNNING, QUEUED, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                guard = PARKED.wait(guard).unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+
+        // The initializer is done, and threads are waiting for it.
+        pub fn wake_waiting() {
+            drop(LOCK.lock().unwrap_or_else(PoisonError::into_inner));
+            PARKED.notify_all();
+        }
+    }
+
+    #[cfg(not(all(feature = "std", not(loom))))]
+    use spin::{wait_running, wake_waiting};
+
+    // Without `std`, or with loom, threads spin until the initializer is done.
+    #[cfg(not(all(feature = "std", not(loom))))]
+    mod spin {
+        use crate::sync::atomic::AtomicU8;
+
+        #[cfg(feature = "std")]
+        crate::cfg_loom! {
+            use std::thread::yield_now as relax;
+        }
+        #[cfg(not(feature = "std"))]
+        crate::cfg_loom! {
+            use core::hint::spin_loop as relax;
+        }
+
+        // Another thread is running the initializer.
+        pub fn wait_running(_: &AtomicU8) {
+            relax()
+        }
+
+        // Spinning threads need no wakeup.
+        pub fn wake_waiting() {}
+    }
+
+    super::impl_once!(sync);
+
+    unsafe impl<T: Send> Send for OnceLock<T> {}
+    unsafe impl<T: Send + Sync> Sync for OnceLock<T> {}
+
+    unsafe impl<T: Send, F: Send> Send for Lazy<T, F> {}
+    unsafe impl<T: Send + Sync, F: Send> Sync for Lazy<T, F> {}
+
+    impl<T: Send + Sync> crate::AssertMt for OnceLock<T> {}
+    impl<T: Send + Sync, F: Send> crate::AssertMt for Lazy<T, F> {}
+}
+
+/// Singlethreaded [`OnceLock`](unsync::OnceLock) and [`Lazy`](unsync::Lazy)
+pub mod unsync {
+    // Only the initializer itself can observe this on a single thread.
+    fn wait_running(_: &crate::unsync::atomic::AtomicU8) {
+        panic!("reentrant init")
+    }
+
+    // No thread can be waiting.
+    fn wake_waiting() {}
+
+    super::impl_once!(unsync);
+}
+
+use crate::cell::UnsafeCell;
+
+const INCOMPLETE: u8 = 0;
+const RUNNING: u8 = 1;
+const COMPLETE: u8 = 2;
+// Running, with threads waiting for it to finish.
+const QUEUED: u8 = 3;
+
+macro_rules! impl_once {
+    ($sync:ident) => {
+        use core::{
+            fmt,
+            mem::{self, MaybeUninit},
+            ops::{Deref, DerefMut},
+            sync::atomic::Ordering,
+        };
+
+        use super::*;
+        use crate::$sync::atomic::AtomicU8;
+
+        // Leave the running state, waking up the threads waiting for it.
+        fn finish(state: &AtomicU8, to: u8) {
+            if state.swap(to, Ordering::AcqRel) == QUEUED {
+                wake_waiting();
+            }
+        }
+
+        /// A cell which can be written to only once.
+        ///
+        /// # Example
+        ///
+        /// ```
+        #[doc = concat!("use synchrony::", stringify!($sync), "::once::OnceLock;")]
+        /// let cell = OnceLock::new();
+        /// assert_eq!(cell.get(), None);
+        /// assert_eq!(cell.get_or_init(|| 42), &42);
+        /// assert_eq!(cell.set(0), Err(0));
+        /// ```
+        pub struct OnceLock<T> {
+            state: AtomicU8,
+            value: UnsafeCell<MaybeUninit<T>>,
+        }
+
+        impl<T> OnceLock<T> {
+            /// Creates a new empty cell.
+            #[cfg(not(loom))]
+            pub const fn new() -> Self {
+                Self {
+                    state: AtomicU8::new(INCOMPLETE),
+                    value: UnsafeCell::new(MaybeUninit::uninit()),
+                }
+            }
+
+            /// Creates a new empty cell.
+            ///
+            /// This `new` is not `const` due to loom not supporting it.
+            #[cfg(loom)]
+            pub fn new() -> Self {
+                Self {
+                    state: AtomicU8::new(INCOMPLETE),
+                    value: UnsafeCell::new(MaybeUninit::uninit()),
+                }
+            }
+
+            /// Gets the reference to the underlying value, or `None` if the cell
+            /// is empty or being initialized.
+            pub fn get(&self) -> Option<&T> {
+                if self.state.load(Ordering::Acquire) == COMPLETE {
+                    Some(unsafe { self.get_unchecked() })
+                } else {
+                    None
+                }
+            }
+
+            /// Gets the mutable reference to the underlying value, or `None` if
+            /// the cell is empty.
+            pub fn get_mut(&mut self) -> Option<&mut T> {
+                if self.state.load(Ordering::Acquire) == COMPLETE {
+                    Some(
+                        self.value
+                            .with_mut(|value| unsafe { (*value).assume_init_mut() }),
+                    )
+                } else {
+                    None
+                }
+            }
+
+            /// Initializes the contents of the cell to `value`.
+            ///
+            /// Returns `Err(value)` if the cell was already initialized.
+            pub fn set(&self, value: T) -> Result<(), T> {
+                let mut value = Some(value);
+                self.get_or_init(|| value.take().unwrap());
+                match value {
+                    None => Ok(()),
+                    Some(value) => Err(value),
+                }
+            }
+
+            /// Gets the contents of the cell, initializing it with `f` if the cell
+            /// was empty.
+            ///
+            /// If `f` panics, the panic is propagated and the cell remains
+            /// uninitialized. Initializing the cell from within `f` is an error:
+            /// it deadlocks for the multithreaded version and panics for the
+            /// singlethreaded one.
+            ///
+            /// If another thread is initializing the cell, this blocks until it's
+            /// done: the thread is parked with the `std` feature, and spins
+            /// otherwise.
+            pub fn get_or_init<F>(&self, f: F) -> &T
+            where
+                F: FnOnce() -> T,
+            {
+                match self.get_or_try_init(|| Ok::<T, core::convert::Infallible>(f())) {
+                    Ok(value) => value,
+                }
+            }
+
+            /// Gets the contents of the cell, initializing it with `f` if the cell
+            /// was empty. If `f` fails, the error is returned and the cell remains
+            /// uninitialized.
+            ///
+            /// See [`get_or_init`](Self::get_or_init) for panics and reentrancy.
+            pub fn get_or_try_init<F, E>(&self, f: F) -> Result<&T, E>
+            where
+                F: FnOnce() -> Result<T, E>,
+            {
+                if let Some(value) = self.get() {
+                    return Ok(value);
+                }
+                loop {
+                    match self.state.compare_exchange(
+                        INCOMPLETE,
+                        RUNNING,
+                        Ordering::Acquire,
+                        Ordering::Acquire,
+                    ) {
+                        Ok(_) => break,
+                        Err(COMPLETE) => return Ok(unsafe { self.get_unchecked() }),
+                        Err(_) => wait_running(&self.state),
+                    }
+                }
+
+                // Give up the initialization if `f` fails or panics.
+                struct Reset<'a>(&'a AtomicU8);
+
+                impl Drop for Reset<'_> {
+                    fn drop(&mut self) {
+                        finish(self.0, INCOMPLETE);
+                    }
+                }
+
+                let reset = Reset(&self.state);
+                let value = f()?;
+                mem::forget(reset);
+                self.value.with_mut(|slot| unsafe { (*slot).write(value) });
+                finish(&self.state, COMPLETE);
+                Ok(unsafe { self.get_unchecked() })
+            }
+
+            /// Consumes the cell, returning the wrapped value, or `None` if the
+            /// cell was empty.
+            pub fn into_inner(mut self) -> Option<T> {
+                self.take()
+            }
+
+            /// Takes the value out of the cell, moving it back to an empty state.
+            pub fn take(&mut self) -> Option<T> {
+                if self.state.load(Ordering::Acquire) == COMPLETE {
+                    self.state.store(INCOMPLETE, Ordering::Relaxed);
+                    Some(
+                        self.value
+                            .with(|value| unsafe { (*value).assume_init_read() }),
+                    )
+                } else {
+                    None
+                }
+            }
+
+            // SAFETY: the cell must be initialized.
+            unsafe fn get_unchecked(&self) -> &T {
+                self.value
+                    .with(|value| unsafe { (*value).assume_init_ref() })
+            }
+        }
+
+        impl<T> Default for OnceLock<T> {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl<T> From<T> for OnceLock<T> {
+            fn from(value: T) -> Self {
+                let cell = Self::new();
+                let _ = cell.set(value);
+                cell
+            }
+        }
+
+        impl<T: fmt::Debug> fmt::Debug for OnceLock<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut d = f.debug_tuple("OnceLock");
+                match self.get() {
+                    Some(value) => d.field(value),
+                    None => d.field(&format_args!("<uninit>")),
+                };
+                d.finish()
+            }
+        }
+
+        impl<T> Drop for OnceLock<T> {
+            fn drop(&mut self) {
+                drop(self.take());
+            }
+        }
+
+        /// A value which is initialized on the first access.
+        ///
+        /// # Example
+        ///
+        /// ```
+        #[doc = concat!("use synchrony::", stringify!($sync), "::once::Lazy;")]
+        /// let lazy = Lazy::new(|| 40 + 2);
+        /// assert_eq!(Lazy::get(&lazy), None);
+        /// assert_eq!(*lazy, 42);
+        /// assert_eq!(Lazy::get(&lazy), Some(&42));
+        /// ```
+        pub struct Lazy<T, F = fn() -> T> {
+            cell: OnceLock<T>,
+            init: UnsafeCell<Option<F>>,
+        }
+
+        impl<T, F: FnOnce() -> T> Lazy<T, F> {
+            /// Creates a new lazy value with the given initializing function.
+            #[cfg(not(loom))]
+            pub const fn new(f: F) -> Self {
+                Self {
+                    cell: OnceLock::new(),
+                    init: UnsafeCell::new(Some(f)),
+                }
+            }
+
+            /// Creates a new lazy value with the given initializing function.
+            ///
+            /// This `new` is not `const` due to loom not supporting it.
+            #[cfg(loom)]
+            pub fn new(f: F) -> Self {
+                Self {
+                    cell: OnceLock::new(),
+                    init: UnsafeCell::new(Some(f)),
+                }
+            }
+
+            /// Forces the evaluation of this lazy value and returns a reference to
+            /// the result.
+            ///
+            /// # Panics
+            ///
+            /// Panics if a previous initialization panicked.
+            pub fn force(this: &Self) -> &T {
+                this.cell.get_or_init(|| {
+                    // Only the initializing caller gets here.
+                    match this.init.with_mut(|init| unsafe { (*init).take() }) {
+                        Some(f) => f(),
+                        None => panic!("Lazy instance has previously been poisoned"),
+                    }
+                })
+            }
+
+            /// Forces the evaluation of this lazy value and returns a mutable
+            /// reference to the result.
+            ///
+            /// # Panics
+            ///
+            /// Panics if a previous initialization panicked.
+            pub fn force_mut(this: &mut Self) -> &mut T {
+                Self::force(this);
+                this.cell.get_mut().unwrap()
+            }
+
+            /// Gets the reference to the result of this lazy value if it was
+            /// initialized, otherwise returns `None`.
+            pub fn get(this: &Self) -> Option<&T> {
+                this.cell.get()
+            }
+
+            /// Gets the mutable reference to the result of this lazy value if it
+            /// was initialized, otherwise returns `None`.
+            pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+                this.cell.get_mut()
+            }
+
+            /// Consumes this lazy value, returning the result if it was
+            /// initialized, or the initializing function otherwise.
+            ///
+            /// # Panics
+            ///
+            /// Panics if a previous initialization panicked.
+            pub fn into_inner(this: Self) -> Result<T, F> {
+                let Self { cell, init } = this;
+                match cell.into_inner() {
+                    Some(value) => Ok(value),
+                    None => Err(init
+                        .into_inner()
+                        .expect("Lazy instance has previously been poisoned")),
+                }
+            }
+        }
+
+        impl<T: Default> Default for Lazy<T> {
+            fn default() -> Self {
+                Self::new(T::default)
+            }
+        }
+
+        impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                Self::force(self)
+            }
+        }
+
+        impl<T, F: FnOnce() -> T> DerefMut for Lazy<T, F> {
+            fn deref_mut(&mut self) -> &mut T {
+                Self::force_mut(self)
+            }
+        }
+
+        impl<T: fmt::Debug, F> fmt::Debug for Lazy<T, F> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut d = f.debug_tuple("Lazy");
+                match self.cell.get() {
+                    Some(value) => d.field(value),
+                    None => d.field(&format_args!("<uninit>")),
+                };
+                d.finish()
+            }
+        }
+    };
+}
+
+use impl_once;
@@ -0,0 +1,535 @@
+//! Per-thread storage, holding one value for each thread that accesses it.
+
+/// Multithreaded [`PerThread`](sync::PerThread), holding one value per thread
+#[cfg(feature = "std")]
+pub mod sync {
+    use alloc::{boxed::Box, vec::Vec};
+    use core::{fmt, iter::FusedIterator, ptr, slice, sync::atomic::Ordering};
+    use std::{
+        cmp::Reverse,
+        collections::BinaryHeap,
+        sync::{Mutex, PoisonError},
+    };
+
+    crate::cfg_loom! {
+        use core::sync::atomic::AtomicPtr;
+    }
+    crate::cfg_loom! {
+        use std::thread_local;
+    }
+
+    use crate::sync::once::OnceLock;
+
+    // Bucket `i` holds the values of the `2^i` threads starting at index `2^i - 1`.
+    const BUCKETS: usize = usize::BITS as usize;
+
+    /// Per-thread storage, in the spirit of the `thread_local` crate.
+    ///
+    /// Each thread gets its own slot, initialized on first access with
+    /// [`get_or`](Self::get_or). All the values can be visited with
+    /// [`iter`](Self::iter), e.g. to aggregate sharded counters.
+    ///
+    /// Threads are identified by a small index, which is reused after a thread
+    /// exits. A new thread may therefore get the value left by an exited one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    ///
+    /// use synchrony::sync::per_thread::PerThread;
+    ///
+    /// let counter = PerThread::new();
+    /// std::thread::scope(|s| {
+    ///     for _ in 0..4 {
+    ///         s.spawn(|| {
+    ///             let count = counter.get_or(|| Cell::new(0));
+    ///             count.set(count.get() + 1);
+    ///         });
+    ///     }
+    /// });
+    /// let total: usize = counter.into_iter().map(Cell::into_inner).sum();
+    /// assert_eq!(total, 4);
+    /// ```
+    pub struct PerThread<T> {
+        buckets: [AtomicPtr<OnceLock<T>>; BUCKETS],
+    }
+
+    impl<T> PerThread<T> {
+        /// Create a new empty [`PerThread`].
+        #[cfg(not(loom))]
+        pub const fn new() -> Self {
+            Self {
+                buckets: [const { AtomicPtr::new(ptr::null_mut()) }; BUCKETS],
+            }
+        }
+
+        /// Create a new empty [`PerThread`].
+        ///
+        /// This `new` is not `const` due to loom not supporting it.
+        #[cfg(loom)]
+        pub fn new() -> Self {
+            Self {
+                buckets: core::array::from_fn(|_| AtomicPtr::new(ptr::null_mut())),
+            }
+        }
+
+        /// Get the value of the current thread, if it has been initialized.
+        pub fn get(&self) -> Option<&T> {
+            let (bucket, offset) = locate(thread_index());
+            let ptr = self.buckets[bucket].load(Ordering::Acquire);
+            if ptr.is_null() {
+                return None;
+            }
+            // SAFETY: allocated buckets stay alive until `self` is dropped.
+            unsafe { &*ptr.add(offset) }.get()
+        }
+
+        /// Get the value of the current thread, initializing it with `f` if it
+        /// hasn't been yet.
+        pub fn get_or<F>(&self, f: F) -> &T
+        where
+            F: FnOnce() -> T,
+        {
+            self.slot().get_or_init(f)
+        }
+
+        /// Get the value of the current thread, initializing it with `f` if it
+        /// hasn't been yet. If `f` fails, the error is returned and the value
+        /// stays uninitialized.
+        pub fn get_or_try<F, E>(&self, f: F) -> Result<&T, E>
+        where
+            F: FnOnce() -> Result<T, E>,
+        {
+            self.slot().get_or_try_init(f)
+        }
+
+        /// Get the value of the current thread, initializing it with
+        /// [`Default`] if it hasn't been yet.
+        pub fn get_or_default(&self) -> &T
+        where
+            T: Default,
+        {
+            self.get_or(T::default)
+        }
+
+        /// Iterate over the values of all threads.
+        pub fn iter(&self) -> Iter<'_, T>
+        where
+            T: Sync,
+        {
+            Iter {
+                buckets: self.buckets.iter().enumerate(),
+                slots: [].iter(),
+            }
+        }
+
+        /// Mutably iterate over the values of all threads.
+        pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+            IterMut {
+                buckets: self.buckets.iter_mut().enumerate(),
+                slots: [].iter_mut(),
+            }
+        }
+
+        fn slot(&self) -> &OnceLock<T> {
+            let (bucket, offset) = locate(thread_index());
+            let slot = &self.buckets[bucket];
+            let mut ptr = slot.load(Ordering::Acquire);
+            if ptr.is_null() {
+                let new = Box::into_raw(
+                    (0..1usize << bucket)
+                        .map(|_| OnceLock::<T>::new())
+                        .collect::<Box<[_]>>(),
+                )
+                .cast::<OnceLock<T>>();
+                match slot.compare_exchange(
+                    ptr::null_mut(),
+                    new,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => ptr = new,
+                    Err(current) => {
+                        // SAFETY: `new` has never been published.
+                        drop(unsafe { Box::from_raw(bucket_slice(new, bucket)) });
+                        ptr = current;
+                    }
+                }
+            }
+            // SAFETY: allocated buckets stay alive until `self` is dropped.
+            unsafe { &*ptr.add(offset) }
+        }
+    }
+
+    impl<T> Drop for PerThread<T> {
+        fn drop(&mut self) {
+            for (bucket, slot) in self.buckets.iter_mut().enumerate() {
+                let ptr = slot.load(Ordering::Relaxed);
+                if !ptr.is_null() {
+                    // SAFETY: the bucket has been allocated by `slot` with this size.
+                    drop(unsafe { Box::from_raw(bucket_slice(ptr, bucket)) });
+                }
+            }
+        }
+    }
+
+    impl<T> Default for PerThread<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T: fmt::Debug> fmt::Debug for PerThread<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("PerThread")
+                .field("local", &self.get())
+                .finish_non_exhaustive()
+        }
+    }
+
+    unsafe impl<T: Send> Send for PerThread<T> {}
+    // Each value is only shared with the thread that owns it, except through
+    // `iter`, which requires `T: Sync`.
+    unsafe impl<T: Send> Sync for PerThread<T> {}
+
+    impl<T: Send> crate::AssertMt for PerThread<T> {}
+
+    impl<'a, T: Sync> IntoIterator for &'a PerThread<T> {
+        type IntoIter = Iter<'a, T>;
+        type Item = &'a T;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter()
+        }
+    }
+
+    impl<'a, T> IntoIterator for &'a mut PerThread<T> {
+        type IntoIter = IterMut<'a, T>;
+        type Item = &'a mut T;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter_mut()
+        }
+    }
+
+    impl<T> IntoIterator for PerThread<T> {
+        type IntoIter = IntoIter<T>;
+        type Item = T;
+
+        fn into_iter(mut self) -> Self::IntoIter {
+            let mut values = Vec::new();
+            for (bucket, slot) in self.buckets.iter_mut().enumerate() {
+                let ptr = slot.load(Ordering::Relaxed);
+                if !ptr.is_null() {
+                    // SAFETY: `self` is owned, and the emptied slots are freed on drop.
+                    let slots = unsafe { &mut *bucket_slice(ptr, bucket) };
+                    values.extend(slots.iter_mut().filter_map(OnceLock::take));
+                }
+            }
+            IntoIter(values.into_iter())
+        }
+    }
+
+    /// Iterator over the values of a [`PerThread`].
+    #[derive(Debug)]
+    pub struct Iter<'a, T> {
+        buckets: core::iter::Enumerate<slice::Iter<'a, AtomicPtr<OnceLock<T>>>>,
+        slots: slice::Iter<'a, OnceLock<T>>,
+    }
+
+    impl<'a, T> Iterator for Iter<'a, T> {
+        type Item = &'a T;
+
+        fn next(&mut self) -> Option<&'a T> {
+            loop {
+                if let Some(slot) = self.slots.next() {
+                    match slot.get() {
+                        Some(value) => return Some(value),
+                        None => continue,
+                    }
+                }
+                let (bucket, slot) = self.buckets.next()?;
+                let ptr = slot.load(Ordering::Acquire);
+                if !ptr.is_null() {
+                    // SAFETY: allocated buckets stay alive until the `PerThread`
+                    // is dropped.
+                    self.slots = unsafe { &*bucket_slice(ptr, bucket) }.iter();
+                }
+            }
+        }
+    }
+
+    impl<T> FusedIterator for Iter<'_, T> {}
+
+    /// Mutable iterator over the values of a [`PerThread`].
+    #[derive(Debug)]
+    pub struct IterMut<'a, T> {
+        buckets: core::iter::Enumerate<slice::IterMut<'a, AtomicPtr<OnceLock<T>>>>,
+        slots: slice::IterMut<'a, OnceLock<T>>,
+    }
+
+    impl<'a, T> Iterator for IterMut<'a, T> {
+        type Item = &'a mut T;
+
+        fn next(&mut self) -> Option<&'a mut T> {
+            loop {
+                if let Some(slot) = self.slots.next() {
+                    match slot.get_mut() {
+                        Some(value) => return Some(value),
+                        None => continue,
+                    }
+                }
+                let (bucket, slot) = self.buckets.next()?;
+                let ptr = slot.load(Ordering::Relaxed);
+                if !ptr.is_null() {
+                    // SAFETY: the `PerThread` is mutably borrowed for `'a`.
+                    self.slots = unsafe { &mut *bucket_slice(ptr, bucket) }.iter_mut();
+                }
+            }
+        }
+    }
+
+    impl<T> FusedIterator for IterMut<'_, T> {}
+
+    /// Owning iterator over the values of a [`PerThread`].
+    #[derive(Debug)]
+    pub struct IntoIter<T>(alloc::vec::IntoIter<T>);
+
+    impl<T> Iterator for IntoIter<T> {
+        type Item = T;
+
+        fn next(&mut self) -> Option<T> {
+            self.0.next()
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.0.size_hint()
+        }
+    }
+
+    impl<T> ExactSizeIterator for IntoIter<T> {}
+
+    impl<T> FusedIterator for IntoIter<T> {}
+
+    fn locate(index: usize) -> (usize, usize) {
+        let n = index + 1;
+        let bucket = (usize::BITS - 1 - n.leading_zeros()) as usize;
+        (bucket, n - (1 << bucket))
+    }
+
+    fn bucket_slice<T>(ptr: *mut OnceLock<T>, bucket: usize) -> *mut [OnceLock<T>] {
+        ptr::slice_from_raw_parts_mut(ptr, 1 << bucket)
+    }
+
+    // Allocator of thread indices, reusing those of exited threads first to keep
+    // the buckets small.
+    static INDICES: Mutex<(usize, BinaryHeap<Reverse<usize>>)> = Mutex::new((0, BinaryHeap::new()));
+
+    struct ThreadIndex(usize);
+
+    impl ThreadIndex {
+        fn new() -> Self {
+            let mut indices = INDICES.lock().unwrap_or_else(PoisonError::into_inner);
+            match indices.1.pop() {
+                Some(Reverse(index)) => Self(index),
+                None => {
+                    indices.0 += 1;
+                    Self(indices.0 - 1)
+                }
+            }
+        }
+    }
+
+    impl Drop for ThreadIndex {
+        fn drop(&mut self) {
+            INDICES
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .1
+                .push(Reverse(self.0));
+        }
+    }
+
+    thread_local! {
+        static THREAD_INDEX: ThreadIndex = ThreadIndex::new();
+    }
+
+    fn thread_index() -> usize {
+        THREAD_INDEX.with(|index| index.0)
+    }
+}
+
+/// Singlethreaded [`PerThread`](unsync::PerThread), holding a single value
+pub mod unsync {
+    use core::{fmt, iter::FusedIterator, option};
+
+    use crate::unsync::once::OnceLock;
+
+    /// Per-thread storage for a single thread, i.e. a single lazily
+    /// initialized value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    ///
+    /// use synchrony::unsync::per_thread::PerThread;
+    ///
+    /// let counter = PerThread::new();
+    /// for _ in 0..4 {
+    ///     let count = counter.get_or(|| Cell::new(0));
+    ///     count.set(count.get() + 1);
+    /// }
+    /// let total: usize = counter.into_iter().map(Cell::into_inner).sum();
+    /// assert_eq!(total, 4);
+    /// ```
+    pub struct PerThread<T>(OnceLock<T>);
+
+    impl<T> PerThread<T> {
+        /// Create a new empty [`PerThread`].
+        #[cfg(not(loom))]
+        pub const fn new() -> Self {
+            Self(OnceLock::new())
+        }
+
+        /// Create a new empty [`PerThread`].
+        ///
+        /// This `new` is not `const` due to loom not supporting it.
+        #[cfg(loom)]
+        pub fn new() -> Self {
+            Self(OnceLock::new())
+        }
+
+        /// Get the value, if it has been initialized.
+        pub fn get(&self) -> Option<&T> {
+            self.0.get()
+        }
+
+        /// Get the value, initializing it with `f` if it hasn't been yet.
+        pub fn get_or<F>(&self, f: F) -> &T
+        where
+            F: FnOnce() -> T,
+        {
+            self.0.get_or_init(f)
+        }
+
+        /// Get the value, initializing it with `f` if it hasn't been yet. If
+        /// `f` fails, the error is returned and the value stays
+        /// uninitialized.
+        pub fn get_or_try<F, E>(&self, f: F) -> Result<&T, E>
+        where
+            F: FnOnce() -> Result<T, E>,
+        {
+            self.0.get_or_try_init(f)
+        }
+
+        /// Get the value, initializing it with [`Default`] if it hasn't been
+        /// yet.
+        pub fn get_or_default(&self) -> &T
+        where
+            T: Default,
+        {
+            self.get_or(T::default)
+        }
+
+        /// Iterate over the value, if it has been initialized.
+        pub fn iter(&self) -> Iter<'_, T> {
+            Iter(self.0.get().into_iter())
+        }
+
+        /// Mutably iterate over the value, if it has been initialized.
+        pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+            IterMut(self.0.get_mut().into_iter())
+        }
+    }
+
+    impl<T> Default for PerThread<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T: fmt::Debug> fmt::Debug for PerThread<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("PerThread")
+                .field("local", &self.get())
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl<'a, T> IntoIterator for &'a PerThread<T> {
+        type IntoIter = Iter<'a, T>;
+        type Item = &'a T;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter()
+        }
+    }
+
+    impl<'a, T> IntoIterator for &'a mut PerThread<T> {
+        type IntoIter = IterMut<'a, T>;
+        type Item = &'a mut T;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter_mut()
+        }
+    }
+
+    impl<T> IntoIterator for PerThread<T> {
+        type IntoIter = IntoIter<T>;
+        type Item = T;
+
+        fn into_iter(self) -> Self::IntoIter {
+            IntoIter(self.0.into_inner().into_iter())
+        }
+    }
+
+    /// Iterator over the value of a [`PerThread`].
+    #[derive(Debug)]
+    pub struct Iter<'a, T>(option::IntoIter<&'a T>);
+
+    impl<'a, T> Iterator for Iter<'a, T> {
+        type Item = &'a T;
+
+        fn next(&mut self) -> Option<&'a T> {
+            self.0.next()
+        }
+    }
+
+    impl<T> FusedIterator for Iter<'_, T> {}
+
+    /// Mutable iterator over the value of a [`PerThread`].
+    #[derive(Debug)]
+    pub struct IterMut<'a, T>(option::IntoIter<&'a mut T>);
+
+    impl<'a, T> Iterator for IterMut<'a, T> {
+        type Item = &'a mut T;
+
+        fn next(&mut self) -> Option<&'a mut T> {
+            self.0.next()
+        }
+    }
+
+    impl<T> FusedIterator for IterMut<'_, T> {}
+
+    /// Owning iterator over the value of a [`PerThread`].
+    #[derive(Debug)]
+    pub struct IntoIter<T>(option::IntoIter<T>);
+
+    impl<T> Iterator for IntoIter<T> {
+        type Item = T;
+
+        fn next(&mut self) -> Option<T> {
+            self.0.next()
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.0.size_hint()
+        }
+    }
+
+    impl<T> ExactSizeIterator for IntoIter<T> {}
+
+    impl<T> FusedIterator for IntoIter<T> {}
+}
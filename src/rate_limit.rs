@@ -0,0 +1,401 @@
+//! Async token-bucket rate limiter, driven by a user-supplied [`Clock`].
+
+/// Multithreaded [`RateLimiter`](sync::RateLimiter)
+pub mod sync {
+    pub use super::Clock;
+
+    super::impl_rate_limit!(sync);
+
+    impl<C: Clock + Send + Sync> crate::AssertMt for RateLimiter<C> {}
+}
+
+/// Singlethreaded [`RateLimiter`](unsync::RateLimiter)
+pub mod unsync {
+    pub use super::Clock;
+
+    super::impl_rate_limit!(unsync);
+}
+
+use core::{future::Future, mem, task::Waker, time::Duration};
+
+use slab::Slab;
+
+/// A source of time for a rate limiter, so that it doesn't depend on any
+/// runtime.
+///
+/// Time is measured as the [`Duration`] elapsed since an arbitrary, fixed
+/// point. It must never go backwards.
+pub trait Clock {
+    /// The future returned by [`sleep`](Self::sleep).
+    type Sleep: Future<Output = ()>;
+
+    /// The current time.
+    fn now(&self) -> Duration;
+
+    /// Wait for `duration` to elapse.
+    fn sleep(&self, duration: Duration) -> Self::Sleep;
+}
+
+// Sentinel for the ends of the queue.
+const NIL: usize = usize::MAX;
+
+struct Entry {
+    waker: Waker,
+    prev: usize,
+    next: usize,
+}
+
+// The tasks waiting for tokens, served in FIFO order, linked through the
+// entries of a `Slab`. Wakers are returned rather than dropped or woken, so
+// that it happens once the bucket is released.
+struct Waiters {
+    entries: Slab<Entry>,
+    head: usize,
+    tail: usize,
+}
+
+impl Waiters {
+    const fn new() -> Self {
+        Self {
+            entries: Slab::new(),
+            head: NIL,
+            tail: NIL,
+        }
+    }
+
+    fn insert(&mut self, waker: &Waker) -> usize {
+        let key = self.entries.insert(Entry {
+            waker: waker.clone(),
+            prev: self.tail,
+            next: NIL,
+        });
+        match self.tail {
+            NIL => self.head = key,
+            tail => self.entries[tail].next = key,
+        }
+        self.tail = key;
+        key
+    }
+
+    // Update the waker of a waiter, returning the replaced one.
+    fn register(&mut self, key: usize, waker: &Waker) -> Option<Waker> {
+        let current = &mut self.entries[key].waker;
+        (!waker.will_wake(current)).then(|| mem::replace(current, waker.clone()))
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn head(&self) -> Option<usize> {
+        (self.head != NIL).then_some(self.head)
+    }
+
+    // Remove a waiter, returning its waker, and that of the next one to wake
+    // if it was at the head of the queue.
+    fn remove(&mut self, key: usize) -> (Waker, Option<Waker>) {
+        let entry = self.entries.remove(key);
+        match entry.prev {
+            NIL => self.head = entry.next,
+            prev => self.entries[prev].next = entry.next,
+        }
+        match entry.next {
+            NIL => self.tail = entry.prev,
+            next => self.entries[next].prev = entry.prev,
+        }
+        let next = match entry.prev {
+            NIL => self.head().map(|head| self.entries[head].waker.clone()),
+            _ => None,
+        };
+        (entry.waker, next)
+    }
+}
+
+// A token bucket, implemented as a generic cell rate algorithm: instead of
+// counting tokens, it tracks the time at which the bucket will be full again.
+struct Bucket {
+    // The time it takes to refill a single token.
+    interval: Duration,
+    // The time it takes to refill the whole bucket.
+    capacity: Duration,
+    full_at: Duration,
+    waiters: Waiters,
+}
+
+impl Bucket {
+    // The bucket is full at the start of time, so it is full on creation.
+    const fn new(rate: u32, period: Duration, burst: u32) -> Self {
+        assert!(rate > 0, "rate must be positive");
+        assert!(burst > 0, "burst must be positive");
+        let interval = period.checked_div(rate).unwrap();
+        Self {
+            interval,
+            capacity: interval.checked_mul(burst).expect("burst overflows"),
+            full_at: Duration::ZERO,
+            waiters: Waiters::new(),
+        }
+    }
+
+    // Take `n` tokens at `now` if there are enough of them, or return how long
+    // to wait until there are.
+    fn take(&mut self, now: Duration, n: u32) -> Result<(), Duration> {
+        let full_at = self.full_at.max(now) + self.interval * n;
+        let limit = now + self.capacity;
+        if full_at <= limit {
+            self.full_at = full_at;
+            Ok(())
+        } else {
+            Err(full_at - limit)
+        }
+    }
+
+    fn available(&self, now: Duration) -> u32 {
+        let missing = self.full_at.saturating_sub(now);
+        match self.capacity.checked_sub(missing) {
+            Some(left) if !self.interval.is_zero() => {
+                (left.as_nanos() / self.interval.as_nanos()) as u32
+            }
+            Some(_) => u32::MAX,
+            None => 0,
+        }
+    }
+}
+
+macro_rules! impl_rate_limit {
+    ($sync:ident) => {
+        use core::{
+            fmt,
+            task::{Context, Poll},
+            time::Duration,
+        };
+
+        use super::Bucket;
+        use crate::$sync::mutex_blocking::RawMutex as Mutex;
+
+        /// An async token-bucket rate limiter.
+        ///
+        /// The bucket holds up to `burst` tokens, and is refilled by `rate`
+        /// tokens every `period`. It starts full.
+        ///
+        /// # Fairness
+        ///
+        /// Tasks waiting for tokens are served in the order they started
+        /// waiting, and [`try_acquire`](Self::try_acquire) fails as long as
+        /// any task is waiting, so that a large request can't be starved by
+        /// smaller ones.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// # futures::executor::block_on(async {
+        /// use std::{cell::Cell, future::Ready, time::Duration};
+        ///
+        #[doc = concat!("use synchrony::", stringify!($sync), "::rate_limit::{Clock, RateLimiter};")]
+        ///
+        /// // A fake clock, which jumps forward when asked to sleep.
+        /// #[derive(Default)]
+        /// struct FakeClock(Cell<Duration>);
+        ///
+        /// impl Clock for FakeClock {
+        ///     type Sleep = Ready<()>;
+        ///
+        ///     fn now(&self) -> Duration {
+        ///         self.0.get()
+        ///     }
+        ///
+        ///     fn sleep(&self, duration: Duration) -> Ready<()> {
+        ///         self.0.set(self.0.get() + duration);
+        ///         std::future::ready(())
+        ///     }
+        /// }
+        ///
+        /// // 10 tokens per second, with bursts of up to 5.
+        /// let limiter = RateLimiter::new(FakeClock::default(), 10, Duration::from_secs(1), 5);
+        /// assert!(limiter.try_acquire(5));
+        /// assert!(!limiter.try_acquire(1));
+        ///
+        /// limiter.acquire(2).await;
+        /// assert_eq!(limiter.clock().now(), Duration::from_millis(200));
+        /// # });
+        /// ```
+        pub struct RateLimiter<C> {
+            clock: C,
+            burst: u32,
+            bucket: Mutex<Bucket>,
+        }
+
+        impl<C: Clock> RateLimiter<C> {
+            /// Create a new rate limiter, refilled by `rate` tokens every
+            /// `period`, and holding up to `burst` tokens.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `rate` or `burst` is zero.
+            #[cfg(not(loom))]
+            pub const fn new(clock: C, rate: u32, period: Duration, burst: u32) -> Self {
+                Self {
+                    clock,
+                    burst,
+                    bucket: Mutex::new(Bucket::new(rate, period, burst)),
+                }
+            }
+
+            /// Create a new rate limiter, refilled by `rate` tokens every
+            /// `period`, and holding up to `burst` tokens.
+            ///
+            /// This `new` is not `const` due to loom not supporting it.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `rate` or `burst` is zero.
+            #[cfg(loom)]
+            pub fn new(clock: C, rate: u32, period: Duration, burst: u32) -> Self {
+                Self {
+                    clock,
+                    burst,
+                    bucket: Mutex::new(Bucket::new(rate, period, burst)),
+                }
+            }
+
+            /// The clock of the rate limiter.
+            pub fn clock(&self) -> &C {
+                &self.clock
+            }
+
+            /// The maximum number of tokens that can be acquired at once.
+            pub fn burst(&self) -> u32 {
+                self.burst
+            }
+
+            /// Number of tokens that could be acquired right now, if no task
+            /// were waiting.
+            pub fn available(&self) -> u32 {
+                let bucket = self.bucket.lock();
+                bucket.available(self.clock.now()).min(self.burst)
+            }
+
+            /// Number of tasks waiting for tokens.
+            pub fn waiters(&self) -> usize {
+                self.bucket.lock().waiters.len()
+            }
+
+            /// Attempt to acquire `n` tokens immediately.
+            ///
+            /// Fails if there aren't enough tokens, if another task is waiting
+            /// for tokens, or if `n` is larger than the burst.
+            pub fn try_acquire(&self, n: u32) -> bool {
+                if n > self.burst {
+                    return false;
+                }
+                let mut bucket = self.bucket.lock();
+                bucket.waiters.is_empty() && bucket.take(self.clock.now(), n).is_ok()
+            }
+
+            /// Acquire `n` tokens, waiting until there are enough of them.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `n` is larger than the burst, as the bucket could
+            /// never hold that many tokens.
+            pub async fn acquire(&self, n: u32) {
+                assert!(
+                    n <= self.burst,
+                    "cannot acquire more tokens than the burst"
+                );
+                let mut waiter = Waiter {
+                    bucket: &self.bucket,
+                    key: None,
+                };
+                loop {
+                    let wait =
+                        core::future::poll_fn(|cx| self.poll_acquire(&mut waiter.key, n, cx))
+                            .await;
+                    match wait {
+                        // Only the head of the queue sleeps, while the others
+                        // wait for it to be done.
+                        Some(duration) => self.clock.sleep(duration).await,
+                        None => return,
+                    }
+                }
+            }
+
+            // Take the tokens if this task is at the head of the queue,
+            // returning how long to sleep if there aren't enough of them.
+            fn poll_acquire(
+                &self,
+                key: &mut Option<usize>,
+                n: u32,
+                cx: &mut Context<'_>,
+            ) -> Poll<Option<Duration>> {
+                let mut bucket = self.bucket.lock();
+                match *key {
+                    None if !bucket.waiters.is_empty() => {
+                        *key = Some(bucket.waiters.insert(cx.waker()));
+                        return Poll::Pending;
+                    }
+                    Some(k) if bucket.waiters.head() != Some(k) => {
+                        let replaced = bucket.waiters.register(k, cx.waker());
+                        drop(bucket);
+                        drop(replaced);
+                        return Poll::Pending;
+                    }
+                    _ => {}
+                }
+                match bucket.take(self.clock.now(), n) {
+                    Ok(()) => {
+                        if let Some(k) = key.take() {
+                            let (removed, next) = bucket.waiters.remove(k);
+                            drop(bucket);
+                            drop(removed);
+                            if let Some(next) = next {
+                                next.wake();
+                            }
+                        }
+                        Poll::Ready(None)
+                    }
+                    Err(duration) => {
+                        if key.is_none() {
+                            *key = Some(bucket.waiters.insert(cx.waker()));
+                        }
+                        Poll::Ready(Some(duration))
+                    }
+                }
+            }
+        }
+
+        impl<C> fmt::Debug for RateLimiter<C> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let bucket = self.bucket.lock();
+                f.debug_struct("RateLimiter")
+                    .field("interval", &bucket.interval)
+                    .field("burst", &self.burst)
+                    .field("waiters", &bucket.waiters.len())
+                    .finish()
+            }
+        }
+
+        // A place in the queue of a `RateLimiter`, removed when dropped.
+        struct Waiter<'a> {
+            bucket: &'a Mutex<Bucket>,
+            key: Option<usize>,
+        }
+
+        impl Drop for Waiter<'_> {
+            fn drop(&mut self) {
+                if let Some(key) = self.key.take() {
+                    let (removed, next) = self.bucket.lock().waiters.remove(key);
+                    drop(removed);
+                    if let Some(next) = next {
+                        next.wake();
+                    }
+                }
+            }
+        }
+    };
+}
+
+use impl_rate_limit;
@@ -0,0 +1,316 @@
+//! Re-armable async events, in the spirit of .NET's `ManualResetEvent` and
+//! `AutoResetEvent`.
+
+/// Multithreaded [`ManualResetEvent`](sync::ManualResetEvent) and
+/// [`AutoResetEvent`](sync::AutoResetEvent)
+pub mod sync {
+    super::impl_reset_event!(sync);
+
+    impl crate::AssertMt for ManualResetEvent {}
+    impl crate::AssertMt for ManualResetEventFuture<'_> {}
+    impl crate::AssertMt for AutoResetEvent {}
+    impl crate::AssertMt for AutoResetEventFuture<'_> {}
+}
+
+/// Singlethreaded [`ManualResetEvent`](unsync::ManualResetEvent) and
+/// [`AutoResetEvent`](unsync::AutoResetEvent)
+pub mod unsync {
+    super::impl_reset_event!(unsync);
+}
+
+macro_rules! impl_reset_event {
+    ($sync:ident) => {
+        use core::{
+            fmt,
+            future::Future,
+            pin::Pin,
+            task::{Context, Poll},
+        };
+
+        use crate::$sync::{
+            flag::Flag,
+            waker_slot::{WakerKey, WakerSet},
+        };
+
+        // The state of an event, and the tasks waiting for it.
+        struct Inner {
+            set: Flag,
+            waiters: WakerSet,
+        }
+
+        impl Inner {
+            #[cfg(not(loom))]
+            const fn new(set: bool) -> Self {
+                Self {
+                    set: Flag::new(set),
+                    waiters: WakerSet::new(),
+                }
+            }
+
+            #[cfg(loom)]
+            fn new(set: bool) -> Self {
+                Self {
+                    set: Flag::new(set),
+                    waiters: WakerSet::new(),
+                }
+            }
+
+            // Poll for the event, with `acquire` checking (and possibly consuming)
+            // the state.
+            fn poll_wait(
+                &self,
+                wait_key: &mut Option<WakerKey>,
+                cx: &mut Context<'_>,
+                acquire: impl Fn(&Flag) -> bool,
+            ) -> Poll<()> {
+                if !acquire(&self.set) {
+                    match *wait_key {
+                        Some(key) => self.waiters.update(key, cx.waker()),
+                        None => *wait_key = Some(self.waiters.insert(cx.waker())),
+                    }
+                    // Check again after registering, so that a `set` racing with
+                    // the registration isn't missed.
+                    if !acquire(&self.set) {
+                        return Poll::Pending;
+                    }
+                }
+                if let Some(key) = wait_key.take() {
+                    self.waiters.remove(key);
+                }
+                Poll::Ready(())
+            }
+        }
+
+        /// An event that releases all its waiters while it is set, until it is
+        /// [`reset`](Self::reset).
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// # futures::executor::block_on(async {
+        #[doc = concat!("use synchrony::", stringify!($sync), "::reset_event::ManualResetEvent;")]
+        /// let event = ManualResetEvent::new(false);
+        /// futures::join!(event.wait(), event.wait(), async { event.set() });
+        /// // Still set, so this resolves immediately.
+        /// event.wait().await;
+        /// event.reset();
+        /// assert!(!event.is_set());
+        /// # });
+        /// ```
+        pub struct ManualResetEvent {
+            inner: Inner,
+        }
+
+        impl ManualResetEvent {
+            /// Create a new event, initially set or not.
+            #[cfg(not(loom))]
+            pub const fn new(set: bool) -> Self {
+                Self {
+                    inner: Inner::new(set),
+                }
+            }
+
+            /// Create a new event, initially set or not.
+            ///
+            /// This `new` is not `const` due to loom not supporting it.
+            #[cfg(loom)]
+            pub fn new(set: bool) -> Self {
+                Self {
+                    inner: Inner::new(set),
+                }
+            }
+
+            /// Returns whether the event is set.
+            pub fn is_set(&self) -> bool {
+                self.inner.set.get()
+            }
+
+            /// Set the event, releasing all the current and future waiters until it
+            /// is reset.
+            pub fn set(&self) {
+                if !self.inner.set.swap(true) {
+                    self.inner.waiters.wake_all();
+                }
+            }
+
+            /// Reset the event, so that new waiters wait for the next
+            /// [`set`](Self::set).
+            pub fn reset(&self) {
+                self.inner.set.swap(false);
+            }
+
+            /// Wait for the event to be set.
+            pub fn wait(&self) -> ManualResetEventFuture<'_> {
+                ManualResetEventFuture {
+                    event: self,
+                    wait_key: None,
+                }
+            }
+        }
+
+        impl Default for ManualResetEvent {
+            fn default() -> Self {
+                Self::new(false)
+            }
+        }
+
+        impl fmt::Debug for ManualResetEvent {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("ManualResetEvent")
+                    .field("is_set", &self.is_set())
+                    .finish()
+            }
+        }
+
+        /// A future which resolves when a [`ManualResetEvent`] is set.
+        pub struct ManualResetEventFuture<'a> {
+            event: &'a ManualResetEvent,
+            wait_key: Option<WakerKey>,
+        }
+
+        impl Future for ManualResetEventFuture<'_> {
+            type Output = ();
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+                let this = self.get_mut();
+                this.event
+                    .inner
+                    .poll_wait(&mut this.wait_key, cx, |set| set.get())
+            }
+        }
+
+        impl Drop for ManualResetEventFuture<'_> {
+            fn drop(&mut self) {
+                if let Some(key) = self.wait_key.take() {
+                    self.event.inner.waiters.remove(key);
+                }
+            }
+        }
+
+        impl fmt::Debug for ManualResetEventFuture<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("ManualResetEventFuture")
+                    .field("event", self.event)
+                    .finish()
+            }
+        }
+
+        /// An event that releases a single waiter each time it is set, and is
+        /// cleared by that waiter.
+        ///
+        /// If there is no waiter when the event is set, it stays set until the
+        /// next waiter comes.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// # futures::executor::block_on(async {
+        #[doc = concat!("use synchrony::", stringify!($sync), "::reset_event::AutoResetEvent;")]
+        /// let event = AutoResetEvent::new(false);
+        /// event.set();
+        /// event.wait().await;
+        /// assert!(!event.is_set());
+        /// assert!(!event.try_wait());
+        /// # });
+        /// ```
+        pub struct AutoResetEvent {
+            inner: Inner,
+        }
+
+        impl AutoResetEvent {
+            /// Create a new event, initially set or not.
+            #[cfg(not(loom))]
+            pub const fn new(set: bool) -> Self {
+                Self {
+                    inner: Inner::new(set),
+                }
+            }
+
+            /// Create a new event, initially set or not.
+            ///
+            /// This `new` is not `const` due to loom not supporting it.
+            #[cfg(loom)]
+            pub fn new(set: bool) -> Self {
+                Self {
+                    inner: Inner::new(set),
+                }
+            }
+
+            /// Returns whether the event is set.
+            pub fn is_set(&self) -> bool {
+                self.inner.set.get()
+            }
+
+            /// Set the event, releasing one waiter.
+            pub fn set(&self) {
+                self.inner.set.swap(true);
+                self.inner.waiters.wake_one();
+            }
+
+            /// Clear the event without waiting, returning whether it was set.
+            pub fn try_wait(&self) -> bool {
+                self.inner.set.swap(false)
+            }
+
+            /// Wait for the event to be set, and clear it.
+            pub fn wait(&self) -> AutoResetEventFuture<'_> {
+                AutoResetEventFuture {
+                    event: self,
+                    wait_key: None,
+                }
+            }
+        }
+
+        impl Default for AutoResetEvent {
+            fn default() -> Self {
+                Self::new(false)
+            }
+        }
+
+        impl fmt::Debug for AutoResetEvent {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("AutoResetEvent")
+                    .field("is_set", &self.is_set())
+                    .finish()
+            }
+        }
+
+        /// A future which resolves when an [`AutoResetEvent`] is set, clearing
+        /// it.
+        pub struct AutoResetEventFuture<'a> {
+            event: &'a AutoResetEvent,
+            wait_key: Option<WakerKey>,
+        }
+
+        impl Future for AutoResetEventFuture<'_> {
+            type Output = ();
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+                let this = self.get_mut();
+                this.event
+                    .inner
+                    .poll_wait(&mut this.wait_key, cx, |set| set.swap(false))
+            }
+        }
+
+        impl Drop for AutoResetEventFuture<'_> {
+            fn drop(&mut self) {
+                // If we were woken but dropped before taking the event, pass it on
+                // to another waiter.
+                if let Some(key) = self.wait_key.take() {
+                    self.event.inner.waiters.cancel(key);
+                }
+            }
+        }
+
+        impl fmt::Debug for AutoResetEventFuture<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("AutoResetEventFuture")
+                    .field("event", self.event)
+                    .finish()
+            }
+        }
+    };
+}
+
+use impl_reset_event;
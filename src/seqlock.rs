@@ -0,0 +1,251 @@
+//! Sequence locks for small, read-mostly data.
+
+/// Multithreaded [`SeqLock`](sync::SeqLock) based on a sequence counter
+pub mod sync {
+    use core::{cell::UnsafeCell, fmt, mem::MaybeUninit, ptr, sync::atomic::Ordering};
+
+    crate::cfg_loom! {
+        use core::sync::atomic::fence;
+    }
+    crate::cfg_loom! {
+        use core::hint::spin_loop;
+    }
+
+    use crate::sync::atomic::AtomicUsize;
+
+    /// A sequence lock, for small [`Copy`] data that is read much more often
+    /// than it is written.
+    ///
+    /// Readers never write to shared memory, so they don't contend with each
+    /// other: they copy the value optimistically and retry if a writer was
+    /// active meanwhile. Writers are serialized, and never wait for readers, so
+    /// a steady stream of writes can starve readers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use synchrony::sync::seqlock::SeqLock;
+    ///
+    /// let offset = SeqLock::new((0u64, 0i64));
+    /// offset.write(|(version, offset)| {
+    ///     *version += 1;
+    ///     *offset = -42;
+    /// });
+    /// assert_eq!(offset.read(), (1, -42));
+    /// ```
+    pub struct SeqLock<T> {
+        // Odd while a writer is active.
+        seq: AtomicUsize,
+        data: UnsafeCell<T>,
+    }
+
+    unsafe impl<T: Copy + Send> Send for SeqLock<T> {}
+    unsafe impl<T: Copy + Send> Sync for SeqLock<T> {}
+
+    impl<T: Copy + Send> crate::AssertMt for SeqLock<T> {}
+
+    impl<T> SeqLock<T> {
+        /// Create a new [`SeqLock`] holding `val`.
+        #[cfg(not(loom))]
+        pub const fn new(val: T) -> Self {
+            Self {
+                seq: AtomicUsize::new(0),
+                data: UnsafeCell::new(val),
+            }
+        }
+
+        /// Create a new [`SeqLock`] holding `val`.
+        ///
+        /// This `new` is not `const` due to loom not supporting it.
+        #[cfg(loom)]
+        pub fn new(val: T) -> Self {
+            Self {
+                seq: AtomicUsize::new(0),
+                data: UnsafeCell::new(val),
+            }
+        }
+
+        /// Consumes the lock, returning the underlying data.
+        pub fn into_inner(self) -> T {
+            self.data.into_inner()
+        }
+
+        /// Returns a mutable reference to the underlying data.
+        pub fn get_mut(&mut self) -> &mut T {
+            self.data.get_mut()
+        }
+    }
+
+    impl<T: Copy> SeqLock<T> {
+        /// Read the current value, retrying while it is being written.
+        pub fn read(&self) -> T {
+            loop {
+                match self.try_read() {
+                    Some(val) => return val,
+                    None => spin_loop(),
+                }
+            }
+        }
+
+        /// Try to read the current value once, returning `None` if it is being
+        /// written.
+        pub fn try_read(&self) -> Option<T> {
+            let seq = self.seq.load(Ordering::Acquire);
+            if seq & 1 != 0 {
+                return None;
+            }
+            // SAFETY: `T: Copy`, and a torn read is discarded below. It's read
+            // as `MaybeUninit`, since a torn `T` may not be a valid value. The
+            // read is volatile so that it can't be assumed to be stable.
+            let val = unsafe { ptr::read_volatile(self.data.get().cast::<MaybeUninit<T>>()) };
+            // Keep the read of `data` before the second read of `seq`.
+            fence(Ordering::Acquire);
+            if self.seq.load(Ordering::Relaxed) == seq {
+                // SAFETY: no writer was active, so the read isn't torn.
+                Some(unsafe { val.assume_init() })
+            } else {
+                None
+            }
+        }
+
+        /// Modify the value with `f`, waiting for other writers to finish.
+        ///
+        /// `f` should be short, since readers spin while it runs.
+        pub fn write<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+            let mut seq = self.seq.load(Ordering::Relaxed);
+            loop {
+                if seq & 1 != 0 {
+                    spin_loop();
+                    seq = self.seq.load(Ordering::Relaxed);
+                    continue;
+                }
+                match self.seq.compare_exchange_weak(
+                    seq,
+                    seq.wrapping_add(1),
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => break,
+                    Err(current) => seq = current,
+                }
+            }
+            // Keep the writes to `data` after marking the write as started.
+            fence(Ordering::Release);
+
+            // Finish the write even if `f` panics, so that readers don't spin
+            // forever.
+            struct Finish<'a>(&'a AtomicUsize, usize);
+
+            impl Drop for Finish<'_> {
+                fn drop(&mut self) {
+                    self.0.store(self.1, Ordering::Release);
+                }
+            }
+
+            let _finish = Finish(&self.seq, seq.wrapping_add(2));
+            // SAFETY: writers are serialized by `seq`, and readers discard what
+            // they read meanwhile.
+            f(unsafe { &mut *self.data.get() })
+        }
+    }
+
+    impl<T: Default> Default for SeqLock<T> {
+        fn default() -> Self {
+            Self::new(T::default())
+        }
+    }
+
+    impl<T> From<T> for SeqLock<T> {
+        fn from(val: T) -> Self {
+            Self::new(val)
+        }
+    }
+
+    impl<T: Copy + fmt::Debug> fmt::Debug for SeqLock<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_tuple("SeqLock").field(&self.read()).finish()
+        }
+    }
+}
+
+/// Singlethreaded [`SeqLock`](unsync::SeqLock) based on [`core::cell::Cell`]
+pub mod unsync {
+    use core::{cell::Cell, fmt};
+
+    /// A singlethreaded counterpart of a sequence lock, i.e. a [`Cell`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use synchrony::unsync::seqlock::SeqLock;
+    ///
+    /// let offset = SeqLock::new((0u64, 0i64));
+    /// offset.write(|(version, offset)| {
+    ///     *version += 1;
+    ///     *offset = -42;
+    /// });
+    /// assert_eq!(offset.read(), (1, -42));
+    /// ```
+    pub struct SeqLock<T> {
+        data: Cell<T>,
+    }
+
+    impl<T> SeqLock<T> {
+        /// Create a new [`SeqLock`] holding `val`.
+        pub const fn new(val: T) -> Self {
+            Self {
+                data: Cell::new(val),
+            }
+        }
+
+        /// Consumes the lock, returning the underlying data.
+        pub fn into_inner(self) -> T {
+            self.data.into_inner()
+        }
+
+        /// Returns a mutable reference to the underlying data.
+        pub fn get_mut(&mut self) -> &mut T {
+            self.data.get_mut()
+        }
+    }
+
+    impl<T: Copy> SeqLock<T> {
+        /// Read the current value.
+        pub fn read(&self) -> T {
+            self.data.get()
+        }
+
+        /// Read the current value. Never fails on a single thread.
+        pub fn try_read(&self) -> Option<T> {
+            Some(self.read())
+        }
+
+        /// Modify the value with `f`.
+        ///
+        /// The modification is only visible once `f` returns.
+        pub fn write<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+            let mut val = self.data.get();
+            let res = f(&mut val);
+            self.data.set(val);
+            res
+        }
+    }
+
+    impl<T: Default> Default for SeqLock<T> {
+        fn default() -> Self {
+            Self::new(T::default())
+        }
+    }
+
+    impl<T> From<T> for SeqLock<T> {
+        fn from(val: T) -> Self {
+            Self::new(val)
+        }
+    }
+
+    impl<T: Copy + fmt::Debug> fmt::Debug for SeqLock<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_tuple("SeqLock").field(&self.read()).finish()
+        }
+    }
+}
@@ -1,20 +1,481 @@
-//! Shared pointer based on [`Arc`] or [`Rc`].
+//! Shared pointer based on [`Arc`] or [`Rc`], and an atomically swappable
+//! version of it.
 //!
-//! [`Arc`]: std::sync::Arc
-//! [`Rc`]: std::rc::Rc
+//! [`Arc`]: alloc::sync::Arc
+//! [`Rc`]: alloc::rc::Rc
 
-/// Multithreaded [`Shared`] based on [`std::sync::Arc`]
+/// Multithreaded [`Shared`] based on [`alloc::sync::Arc`]
 ///
 /// [`Shared`]: sync::Shared
 pub mod sync {
+    use core::{
+        fmt,
+        marker::PhantomData,
+        mem::{self, ManuallyDrop},
+        ops::Deref,
+        ptr,
+        sync::atomic::Ordering,
+    };
+
+    crate::cfg_loom! {
+        pub use alloc::sync::Arc as Shared;
+    }
     crate::cfg_loom! {
-        pub use std::sync::Arc as Shared;
+        use core::sync::atomic::AtomicPtr;
+    }
+    use crate::sync::{atomic::AtomicUsize, mutex_blocking::RawMutex as Mutex, spin::Backoff};
+
+    // Number of guards that can borrow the value of a `SharedSwap` at once.
+    // Further guards hold a strong reference instead.
+    const DEBTS: usize = 8;
+
+    /// A [`Shared`] pointer that can be atomically swapped, in the spirit of
+    /// `arc-swap`.
+    ///
+    /// Readers never block: [`load`](Self::load) and
+    /// [`load_guard`](Self::load_guard) are lock-free. Writers are serialized,
+    /// and wait for the readers that are in the middle of loading the old value
+    /// before releasing it. They never wait for outstanding
+    /// [`SharedSwapGuard`]s, which are handed a strong reference to the old
+    /// value instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use synchrony::sync::shared::{Shared, SharedSwap};
+    ///
+    /// let config = SharedSwap::from_pointee(1);
+    /// let old = config.load();
+    /// config.store(Shared::new(2));
+    /// assert_eq!(*old, 1);
+    /// assert_eq!(*config.load_guard(), 2);
+    /// ```
+    pub struct SharedSwap<T> {
+        ptr: AtomicPtr<T>,
+        // Readers register themselves in `readers[epoch & 1]` while they access
+        // `ptr`. After swapping `ptr`, a writer bumps `epoch` and waits for the
+        // readers of the previous epoch to leave, so it can't starve on new ones.
+        epoch: AtomicUsize,
+        readers: [AtomicUsize; 2],
+        // The values borrowed by guards, which writers pay back with a strong
+        // reference before releasing them, replacing them with `paid()`.
+        debts: [AtomicPtr<T>; DEBTS],
+        writer: Mutex<()>,
+        _marker: PhantomData<Shared<T>>,
+    }
+
+    impl<T> SharedSwap<T> {
+        /// Create a new [`SharedSwap`] holding `val`.
+        pub fn new(val: Shared<T>) -> Self {
+            Self {
+                ptr: AtomicPtr::new(Shared::into_raw(val).cast_mut()),
+                epoch: AtomicUsize::new(0),
+                readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+                debts: core::array::from_fn(|_| AtomicPtr::new(ptr::null_mut())),
+                writer: Mutex::new(()),
+                _marker: PhantomData,
+            }
+        }
+
+        /// Create a new [`SharedSwap`] holding a new [`Shared`] of `val`.
+        pub fn from_pointee(val: T) -> Self {
+            Self::new(Shared::new(val))
+        }
+
+        /// Load the current value.
+        pub fn load(&self) -> Shared<T> {
+            let epoch = self.enter();
+            let ptr = self.ptr.load(Ordering::SeqCst);
+            // SAFETY: `ptr` can't be released while we're registered as a reader.
+            let val = unsafe { clone_raw(ptr) };
+            self.leave(epoch);
+            val
+        }
+
+        /// Borrow the current value, usually without touching its reference
+        /// count.
+        ///
+        /// Writers don't wait for the returned guard: if they replace the value
+        /// in the meantime, they hand the guard a strong reference to it. The
+        /// guard may thus be held for long, e.g. across an `.await`, and the
+        /// same thread may store a new value while holding it.
+        ///
+        /// Only a few guards can borrow the value at once, after which new
+        /// guards clone it like [`load`](Self::load).
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// use synchrony::sync::shared::{Shared, SharedSwap};
+        ///
+        /// let config = SharedSwap::from_pointee(1);
+        /// let guard = config.load_guard();
+        /// config.store(Shared::new(2));
+        /// assert_eq!(*guard, 1);
+        /// assert_eq!(*config.load_guard(), 2);
+        /// ```
+        pub fn load_guard(&self) -> SharedSwapGuard<'_, T> {
+            let epoch = self.enter();
+            let ptr = self.ptr.load(Ordering::SeqCst);
+            // Borrowing `ptr` must be visible to writers before leaving, so
+            // that the writer replacing it pays the debt.
+            let debt = self.debts.iter().position(|debt| {
+                debt.compare_exchange(ptr::null_mut(), ptr, Ordering::SeqCst, Ordering::Relaxed)
+                    .is_ok()
+            });
+            if debt.is_none() {
+                // SAFETY: `ptr` can't be released while we're registered as a
+                // reader.
+                mem::forget(unsafe { clone_raw(ptr) });
+            }
+            self.leave(epoch);
+            SharedSwapGuard {
+                swap: self,
+                debt,
+                ptr,
+            }
+        }
+
+        /// Replace the current value.
+        pub fn store(&self, val: Shared<T>) {
+            drop(self.swap(val));
+        }
+
+        /// Replace the current value, returning the previous one.
+        pub fn swap(&self, val: Shared<T>) -> Shared<T> {
+            let _writer = self.writer.lock();
+            let old = self
+                .ptr
+                .swap(Shared::into_raw(val).cast_mut(), Ordering::SeqCst);
+            self.synchronize(old);
+            // SAFETY: `old` has been unpublished and no reader can access it.
+            unsafe { Shared::from_raw(old) }
+        }
+
+        /// Replace the current value with `new` if it is the same pointer as
+        /// `current`.
+        ///
+        /// Returns the previous value, which is [`Shared::ptr_eq`] to `current`
+        /// if the swap happened.
+        pub fn compare_and_swap(&self, current: &Shared<T>, new: Shared<T>) -> Shared<T> {
+            let _writer = self.writer.lock();
+            let old = self.ptr.load(Ordering::SeqCst);
+            if !ptr::eq(old, Shared::as_ptr(current)) {
+                // SAFETY: writers are serialized, so `old` can't be released under
+                // us.
+                return unsafe { clone_raw(old) };
+            }
+            self.ptr
+                .store(Shared::into_raw(new).cast_mut(), Ordering::SeqCst);
+            self.synchronize(old);
+            // SAFETY: `old` has been unpublished and no reader can access it.
+            unsafe { Shared::from_raw(old) }
+        }
+
+        /// Read-copy-update: replace the current value with `f(current)`,
+        /// retrying with the new current value if it changed in the meantime.
+        ///
+        /// Returns the replaced value. `f` may be called more than once.
+        pub fn rcu<R, F>(&self, mut f: F) -> Shared<T>
+        where
+            F: FnMut(&Shared<T>) -> R,
+            R: Into<Shared<T>>,
+        {
+            let mut current = self.load();
+            loop {
+                let prev = self.compare_and_swap(&current, f(&current).into());
+                if Shared::ptr_eq(&prev, &current) {
+                    return prev;
+                }
+                current = prev;
+            }
+        }
+
+        /// Consume this [`SharedSwap`], returning the current value.
+        pub fn into_inner(self) -> Shared<T> {
+            let ptr = self.ptr.swap(ptr::null_mut(), Ordering::Acquire);
+            // SAFETY: we own `self`, and `Drop` skips the null pointer.
+            unsafe { Shared::from_raw(ptr) }
+        }
+
+        fn enter(&self) -> usize {
+            loop {
+                let epoch = self.epoch.load(Ordering::SeqCst);
+                self.readers[epoch & 1].fetch_add(1, Ordering::SeqCst);
+                // A writer that bumped the epoch in between won't wait for us.
+                if self.epoch.load(Ordering::SeqCst) == epoch {
+                    return epoch & 1;
+                }
+                self.readers[epoch & 1].fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        fn leave(&self, epoch: usize) {
+            self.readers[epoch].fetch_sub(1, Ordering::Release);
+        }
+
+        // Wait for the readers that may have loaded `old`, which has just been
+        // replaced, then pay the debts of the guards that borrowed it.
+        fn synchronize(&self, old: *mut T) {
+            let epoch = self.epoch.fetch_add(1, Ordering::SeqCst) & 1;
+            let backoff = Backoff::new();
+            while self.readers[epoch].load(Ordering::SeqCst) != 0 {
+                backoff.snooze();
+            }
+            for debt in &self.debts {
+                if debt.load(Ordering::SeqCst) == old {
+                    // SAFETY: the caller still holds the reference of `old`.
+                    let val = unsafe { clone_raw(old) };
+                    // The guard may have been dropped in the meantime.
+                    if debt
+                        .compare_exchange(old, paid(), Ordering::SeqCst, Ordering::Relaxed)
+                        .is_ok()
+                    {
+                        mem::forget(val);
+                    }
+                }
+            }
+        }
     }
+
+    // Marks a debt that has been paid, and can't be a pointer from
+    // `Shared::into_raw`, which points into an allocation with the counters.
+    fn paid<T>() -> *mut T {
+        ptr::dangling_mut()
+    }
+
+    // SAFETY: `ptr` must be a live pointer obtained from `Shared::into_raw`.
+    unsafe fn clone_raw<T>(ptr: *const T) -> Shared<T> {
+        let val = ManuallyDrop::new(unsafe { Shared::from_raw(ptr) });
+        Shared::clone(&val)
+    }
+
+    impl<T> Drop for SharedSwap<T> {
+        fn drop(&mut self) {
+            let ptr = self.ptr.load(Ordering::Acquire);
+            if !ptr.is_null() {
+                // SAFETY: we own the last reference stored in `self`.
+                drop(unsafe { Shared::from_raw(ptr) });
+            }
+        }
+    }
+
+    impl<T> From<Shared<T>> for SharedSwap<T> {
+        fn from(val: Shared<T>) -> Self {
+            Self::new(val)
+        }
+    }
+
+    impl<T: Default> Default for SharedSwap<T> {
+        fn default() -> Self {
+            Self::from_pointee(T::default())
+        }
+    }
+
+    impl<T: fmt::Debug> fmt::Debug for SharedSwap<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_tuple("SharedSwap")
+                .field(&*self.load_guard())
+                .finish()
+        }
+    }
+
+    /// A borrow of the value in a [`SharedSwap`], returned by
+    /// [`SharedSwap::load_guard`].
+    pub struct SharedSwapGuard<'a, T> {
+        swap: &'a SharedSwap<T>,
+        // The slot of the debt borrowing `ptr`, or `None` if the guard holds
+        // a strong reference.
+        debt: Option<usize>,
+        ptr: *mut T,
+    }
+
+    impl<T> Deref for SharedSwapGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            // SAFETY: `ptr` can't be released before its debt is paid.
+            unsafe { &*self.ptr }
+        }
+    }
+
+    impl<T> Drop for SharedSwapGuard<'_, T> {
+        fn drop(&mut self) {
+            if let Some(debt) = self.debt {
+                let debt = &self.swap.debts[debt];
+                if debt
+                    .compare_exchange(
+                        self.ptr,
+                        ptr::null_mut(),
+                        Ordering::SeqCst,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+                {
+                    return;
+                }
+                // A writer paid the debt with a strong reference, which is ours
+                // to release.
+                debt.store(ptr::null_mut(), Ordering::SeqCst);
+            }
+            // SAFETY: we own a strong reference to `ptr`.
+            drop(unsafe { Shared::from_raw(self.ptr) });
+        }
+    }
+
+    impl<T: fmt::Debug> fmt::Debug for SharedSwapGuard<'_, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Debug::fmt(&**self, f)
+        }
+    }
+
+    unsafe impl<T: Send + Sync> Send for SharedSwapGuard<'_, T> {}
+    unsafe impl<T: Send + Sync> Sync for SharedSwapGuard<'_, T> {}
+
+    impl<T: Send + Sync> crate::AssertMt for SharedSwap<T> {}
 }
 
-/// Singlethreaded [`Shared`] based on [`std::rc::Rc`]
+/// Singlethreaded [`Shared`] based on [`alloc::rc::Rc`]
 ///
 /// [`Shared`]: unsync::Shared
 pub mod unsync {
-    pub use std::rc::Rc as Shared;
+    pub use alloc::rc::Rc as Shared;
+    use core::{cell::RefCell, fmt, marker::PhantomData, ops::Deref};
+
+    /// A [`Shared`] pointer that can be swapped, with the same API as the
+    /// multithreaded `SharedSwap`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use synchrony::unsync::shared::{Shared, SharedSwap};
+    ///
+    /// let config = SharedSwap::from_pointee(1);
+    /// let old = config.load();
+    /// config.store(Shared::new(2));
+    /// assert_eq!(*old, 1);
+    /// assert_eq!(*config.load_guard(), 2);
+    /// ```
+    pub struct SharedSwap<T> {
+        inner: RefCell<Shared<T>>,
+    }
+
+    impl<T> SharedSwap<T> {
+        /// Create a new [`SharedSwap`] holding `val`.
+        pub const fn new(val: Shared<T>) -> Self {
+            Self {
+                inner: RefCell::new(val),
+            }
+        }
+
+        /// Create a new [`SharedSwap`] holding a new [`Shared`] of `val`.
+        pub fn from_pointee(val: T) -> Self {
+            Self::new(Shared::new(val))
+        }
+
+        /// Load the current value.
+        pub fn load(&self) -> Shared<T> {
+            self.inner.borrow().clone()
+        }
+
+        /// Borrow the current value.
+        ///
+        /// Cloning an [`Rc`](alloc::rc::Rc) is cheap already, so the guard
+        /// just holds one.
+        pub fn load_guard(&self) -> SharedSwapGuard<'_, T> {
+            SharedSwapGuard {
+                val: self.load(),
+                _marker: PhantomData,
+            }
+        }
+
+        /// Replace the current value.
+        pub fn store(&self, val: Shared<T>) {
+            drop(self.swap(val));
+        }
+
+        /// Replace the current value, returning the previous one.
+        pub fn swap(&self, val: Shared<T>) -> Shared<T> {
+            self.inner.replace(val)
+        }
+
+        /// Replace the current value with `new` if it is the same pointer as
+        /// `current`.
+        ///
+        /// Returns the previous value, which is [`Shared::ptr_eq`] to `current`
+        /// if the swap happened.
+        pub fn compare_and_swap(&self, current: &Shared<T>, new: Shared<T>) -> Shared<T> {
+            let mut inner = self.inner.borrow_mut();
+            if Shared::ptr_eq(&inner, current) {
+                core::mem::replace(&mut *inner, new)
+            } else {
+                inner.clone()
+            }
+        }
+
+        /// Read-copy-update: replace the current value with `f(current)`,
+        /// retrying with the new current value if it changed in the meantime.
+        ///
+        /// Returns the replaced value. `f` may be called more than once.
+        pub fn rcu<R, F>(&self, mut f: F) -> Shared<T>
+        where
+            F: FnMut(&Shared<T>) -> R,
+            R: Into<Shared<T>>,
+        {
+            let mut current = self.load();
+            loop {
+                let prev = self.compare_and_swap(&current, f(&current).into());
+                if Shared::ptr_eq(&prev, &current) {
+                    return prev;
+                }
+                current = prev;
+            }
+        }
+
+        /// Consume this [`SharedSwap`], returning the current value.
+        pub fn into_inner(self) -> Shared<T> {
+            self.inner.into_inner()
+        }
+    }
+
+    impl<T> From<Shared<T>> for SharedSwap<T> {
+        fn from(val: Shared<T>) -> Self {
+            Self::new(val)
+        }
+    }
+
+    impl<T: Default> Default for SharedSwap<T> {
+        fn default() -> Self {
+            Self::from_pointee(T::default())
+        }
+    }
+
+    impl<T: fmt::Debug> fmt::Debug for SharedSwap<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_tuple("SharedSwap")
+                .field(&*self.load_guard())
+                .finish()
+        }
+    }
+
+    /// A borrow of the value in a [`SharedSwap`], returned by
+    /// [`SharedSwap::load_guard`].
+    pub struct SharedSwapGuard<'a, T> {
+        val: Shared<T>,
+        _marker: PhantomData<&'a SharedSwap<T>>,
+    }
+
+    impl<T> Deref for SharedSwapGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            &self.val
+        }
+    }
+
+    impl<T: fmt::Debug> fmt::Debug for SharedSwapGuard<'_, T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Debug::fmt(&**self, f)
+        }
+    }
 }
@@ -0,0 +1,290 @@
+//! Spinlocks for very short critical sections, and a [`Backoff`] helper for
+//! spin loops.
+
+/// Multithreaded [`SpinMutex`](sync::SpinMutex) based on
+/// [`AtomicBool`](crate::sync::atomic::AtomicBool)
+pub mod sync {
+    pub use super::Backoff;
+
+    // Another thread holds the lock.
+    fn wait_locked(backoff: &Backoff) {
+        backoff.snooze()
+    }
+
+    super::impl_spin!(sync);
+
+    unsafe impl<T: ?Sized + Send> Send for SpinMutex<T> {}
+    unsafe impl<T: ?Sized + Send> Sync for SpinMutex<T> {}
+
+    impl<T: Send> crate::AssertMt for SpinMutex<T> {}
+}
+
+/// Singlethreaded [`SpinMutex`](unsync::SpinMutex), which panics instead of
+/// spinning like a [`RefCell`](core::cell::RefCell)
+pub mod unsync {
+    pub use super::Backoff;
+
+    // Only the current thread can hold the lock, so it would never be released.
+    fn wait_locked(_: &Backoff) {
+        panic!("already locked")
+    }
+
+    super::impl_spin!(unsync);
+}
+
+use core::cell::Cell;
+
+crate::cfg_loom! {
+    use core::hint::spin_loop;
+}
+#[cfg(feature = "std")]
+crate::cfg_loom! {
+    use std::thread::yield_now;
+}
+
+const SPIN_LIMIT: u32 = 6;
+const YIELD_LIMIT: u32 = 10;
+
+/// Exponential backoff for spin loops, in the spirit of crossbeam's.
+///
+/// Use [`spin`](Self::spin) after a failed compare-and-swap, and
+/// [`snooze`](Self::snooze) while waiting for another thread to make progress.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use synchrony::sync::spin::Backoff;
+///
+/// fn fetch_double(a: &AtomicUsize) -> usize {
+///     let backoff = Backoff::new();
+///     let mut current = a.load(Ordering::Relaxed);
+///     loop {
+///         match a.compare_exchange_weak(current, current * 2, Ordering::AcqRel, Ordering::Relaxed)
+///         {
+///             Ok(previous) => return previous,
+///             Err(previous) => current = previous,
+///         }
+///         backoff.spin();
+///     }
+/// }
+///
+/// let a = AtomicUsize::new(21);
+/// assert_eq!(fetch_double(&a), 21);
+/// assert_eq!(a.load(Ordering::Relaxed), 42);
+/// ```
+#[derive(Debug, Default)]
+pub struct Backoff {
+    step: Cell<u32>,
+}
+
+impl Backoff {
+    /// Create a new [`Backoff`].
+    pub const fn new() -> Self {
+        Self { step: Cell::new(0) }
+    }
+
+    /// Reset the backoff to its initial state.
+    pub fn reset(&self) {
+        self.step.set(0);
+    }
+
+    /// Back off after a failed compare-and-swap, spinning for exponentially
+    /// longer each time.
+    pub fn spin(&self) {
+        for _ in 0..1 << self.step.get().min(SPIN_LIMIT) {
+            spin_loop();
+        }
+        if self.step.get() <= SPIN_LIMIT {
+            self.step.set(self.step.get() + 1);
+        }
+    }
+
+    /// Back off while waiting for another thread, spinning at first, then
+    /// yielding the thread to the OS scheduler.
+    ///
+    /// Without the `std` feature, this only ever spins.
+    pub fn snooze(&self) {
+        if self.step.get() <= SPIN_LIMIT {
+            for _ in 0..1 << self.step.get() {
+                spin_loop();
+            }
+        } else {
+            #[cfg(feature = "std")]
+            yield_now();
+            #[cfg(not(feature = "std"))]
+            for _ in 0..1 << SPIN_LIMIT {
+                spin_loop();
+            }
+        }
+        if self.step.get() <= YIELD_LIMIT {
+            self.step.set(self.step.get() + 1);
+        }
+    }
+
+    /// Returns whether backing off has gone on for long enough that blocking
+    /// the thread would be better.
+    pub fn is_completed(&self) -> bool {
+        self.step.get() > YIELD_LIMIT
+    }
+}
+
+macro_rules! impl_spin {
+    ($sync:ident) => {
+        use core::{
+            cell::UnsafeCell,
+            fmt,
+            marker::PhantomData,
+            ops::{Deref, DerefMut},
+            sync::atomic::Ordering,
+        };
+
+        use crate::$sync::atomic::AtomicBool;
+
+        /// A spinlock, for critical sections of a few instructions.
+        ///
+        /// # Example
+        ///
+        /// ```
+        #[doc = concat!("use synchrony::", stringify!($sync), "::spin::SpinMutex;")]
+        /// let counter = SpinMutex::new(0);
+        /// *counter.lock() += 1;
+        /// assert_eq!(*counter.try_lock().unwrap(), 1);
+        /// ```
+        pub struct SpinMutex<T: ?Sized> {
+            locked: AtomicBool,
+            value: UnsafeCell<T>,
+        }
+
+        impl<T> SpinMutex<T> {
+            /// Creates a new spinlock in an unlocked state ready for use.
+            #[cfg(not(loom))]
+            pub const fn new(val: T) -> Self {
+                Self {
+                    locked: AtomicBool::new(false),
+                    value: UnsafeCell::new(val),
+                }
+            }
+
+            /// Creates a new spinlock in an unlocked state ready for use.
+            ///
+            /// This `new` is not `const` due to loom not supporting it.
+            #[cfg(loom)]
+            pub fn new(val: T) -> Self {
+                Self {
+                    locked: AtomicBool::new(false),
+                    value: UnsafeCell::new(val),
+                }
+            }
+
+            /// Consumes this spinlock, returning the underlying data.
+            pub fn into_inner(self) -> T {
+                self.value.into_inner()
+            }
+        }
+
+        impl<T: ?Sized> SpinMutex<T> {
+            /// Acquires the lock, spinning until it is able to do so.
+            ///
+            /// # Panics
+            ///
+            /// The singlethreaded version panics if the lock is already held,
+            /// like [`RefCell::borrow_mut`](core::cell::RefCell::borrow_mut).
+            pub fn lock(&self) -> SpinMutexGuard<'_, T> {
+                let backoff = Backoff::new();
+                loop {
+                    if let Some(guard) = self.try_lock() {
+                        return guard;
+                    }
+                    while self.locked.load(Ordering::Relaxed) {
+                        wait_locked(&backoff);
+                    }
+                }
+            }
+
+            /// Attempts to acquire the lock immediately, returning `None` if it is
+            /// held.
+            pub fn try_lock(&self) -> Option<SpinMutexGuard<'_, T>> {
+                if self.locked.swap(true, Ordering::Acquire) {
+                    None
+                } else {
+                    Some(SpinMutexGuard {
+                        mutex: self,
+                        _marker: PhantomData,
+                    })
+                }
+            }
+
+            /// Returns whether the lock is held.
+            pub fn is_locked(&self) -> bool {
+                self.locked.load(Ordering::Relaxed)
+            }
+
+            /// Returns a mutable reference to the underlying data.
+            pub fn get_mut(&mut self) -> &mut T {
+                self.value.get_mut()
+            }
+        }
+
+        impl<T: Default> Default for SpinMutex<T> {
+            fn default() -> Self {
+                Self::new(T::default())
+            }
+        }
+
+        impl<T> From<T> for SpinMutex<T> {
+            fn from(val: T) -> Self {
+                Self::new(val)
+            }
+        }
+
+        impl<T: ?Sized + fmt::Debug> fmt::Debug for SpinMutex<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut d = f.debug_struct("SpinMutex");
+                match self.try_lock() {
+                    Some(guard) => d.field("value", &&*guard),
+                    None => d.field("value", &format_args!("<locked>")),
+                };
+                d.finish()
+            }
+        }
+
+        /// An RAII guard of a [`SpinMutex`], which unlocks it when dropped.
+        pub struct SpinMutexGuard<'a, T: ?Sized> {
+            mutex: &'a SpinMutex<T>,
+            // Access to `T` follows the rules of `&mut T`.
+            _marker: PhantomData<&'a mut T>,
+        }
+
+        impl<T: ?Sized> Deref for SpinMutexGuard<'_, T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                // SAFETY: we hold the lock.
+                unsafe { &*self.mutex.value.get() }
+            }
+        }
+
+        impl<T: ?Sized> DerefMut for SpinMutexGuard<'_, T> {
+            fn deref_mut(&mut self) -> &mut T {
+                // SAFETY: we hold the lock.
+                unsafe { &mut *self.mutex.value.get() }
+            }
+        }
+
+        impl<T: ?Sized> Drop for SpinMutexGuard<'_, T> {
+            fn drop(&mut self) {
+                self.mutex.locked.store(false, Ordering::Release);
+            }
+        }
+
+        impl<T: ?Sized + fmt::Debug> fmt::Debug for SpinMutexGuard<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                (**self).fmt(f)
+            }
+        }
+    };
+}
+
+use impl_spin;
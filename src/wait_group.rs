@@ -0,0 +1,293 @@
+//! Wait for a group of tasks to finish.
+
+/// Multithreaded [`WaitGroup`](sync::WaitGroup) and [`Latch`](sync::Latch)
+pub mod sync {
+    super::impl_wait_group!(sync);
+
+    impl crate::AssertMt for WaitGroup {}
+    impl crate::AssertMt for WaitGroupFuture {}
+    impl crate::AssertMt for Latch {}
+    impl crate::AssertMt for LatchFuture<'_> {}
+}
+
+/// Singlethreaded [`WaitGroup`](unsync::WaitGroup) and [`Latch`](unsync::Latch)
+pub mod unsync {
+    super::impl_wait_group!(unsync);
+}
+
+macro_rules! impl_wait_group {
+    ($sync:ident) => {
+        use core::{
+            fmt,
+            future::Future,
+            pin::Pin,
+            sync::atomic::Ordering,
+            task::{Context, Poll},
+        };
+
+        use crate::$sync::{
+            atomic::AtomicUsize,
+            shared::Shared,
+            waker_slot::{WakerKey, WakerSet},
+        };
+
+        // A counter, and the tasks waiting for it to reach zero. Once it does, it
+        // never goes up again.
+        struct Counter {
+            count: AtomicUsize,
+            waiters: WakerSet,
+        }
+
+        impl Counter {
+            fn new(count: usize) -> Self {
+                Self {
+                    count: AtomicUsize::new(count),
+                    waiters: WakerSet::new(),
+                }
+            }
+
+            fn count(&self) -> usize {
+                self.count.load(Ordering::Acquire)
+            }
+
+            fn count_down(&self) {
+                if self
+                    .count
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                        count.checked_sub(1)
+                    })
+                    == Ok(1)
+                {
+                    self.waiters.wake_all();
+                }
+            }
+
+            fn poll_wait(&self, wait_key: &mut Option<WakerKey>, cx: &mut Context<'_>) -> Poll<()> {
+                if self.count() != 0 {
+                    match *wait_key {
+                        Some(key) => self.waiters.update(key, cx.waker()),
+                        None => *wait_key = Some(self.waiters.insert(cx.waker())),
+                    }
+                    // Check again after registering, so that a `wake_all` racing
+                    // with the registration isn't missed.
+                    if self.count() != 0 {
+                        return Poll::Pending;
+                    }
+                }
+                self.cancel(wait_key);
+                Poll::Ready(())
+            }
+
+            fn cancel(&self, wait_key: &mut Option<WakerKey>) {
+                if let Some(key) = wait_key.take() {
+                    self.waiters.remove(key);
+                }
+            }
+        }
+
+        /// A group of tasks to wait for, like Go's `sync.WaitGroup`.
+        ///
+        /// Each clone of a [`WaitGroup`] is a member of the group, and dropping it
+        /// marks it as done. [`wait`](Self::wait) leaves the group and resolves
+        /// once all the other members are done.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// # futures::executor::block_on(async {
+        #[doc = concat!("use synchrony::", stringify!($sync), "::wait_group::WaitGroup;")]
+        /// let wg = WaitGroup::new();
+        /// let tasks = (0..4)
+        ///     .map(|_| {
+        ///         let wg = wg.clone();
+        ///         async move {
+        ///             // ... do some work ...
+        ///             drop(wg);
+        ///         }
+        ///     })
+        ///     .collect::<Vec<_>>();
+        ///
+        /// futures::join!(futures::future::join_all(tasks), wg.wait());
+        /// # });
+        /// ```
+        pub struct WaitGroup {
+            inner: Shared<Counter>,
+        }
+
+        impl WaitGroup {
+            /// Create a new group with a single member.
+            pub fn new() -> Self {
+                Self {
+                    inner: Shared::new(Counter::new(1)),
+                }
+            }
+
+            /// Number of members of the group that are not done yet.
+            pub fn count(&self) -> usize {
+                self.inner.count()
+            }
+
+            /// Leave the group, and wait for all the other members to be done.
+            pub fn wait(self) -> WaitGroupFuture {
+                let inner = self.inner.clone();
+                drop(self);
+                WaitGroupFuture {
+                    inner,
+                    wait_key: None,
+                }
+            }
+        }
+
+        impl Default for WaitGroup {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl Clone for WaitGroup {
+            fn clone(&self) -> Self {
+                self.inner.count.fetch_add(1, Ordering::Relaxed);
+                Self {
+                    inner: self.inner.clone(),
+                }
+            }
+        }
+
+        impl Drop for WaitGroup {
+            fn drop(&mut self) {
+                self.inner.count_down();
+            }
+        }
+
+        impl fmt::Debug for WaitGroup {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("WaitGroup")
+                    .field("count", &self.count())
+                    .finish()
+            }
+        }
+
+        /// A future which resolves when all the members of a [`WaitGroup`] are
+        /// done.
+        pub struct WaitGroupFuture {
+            inner: Shared<Counter>,
+            wait_key: Option<WakerKey>,
+        }
+
+        impl Future for WaitGroupFuture {
+            type Output = ();
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+                let this = self.get_mut();
+                this.inner.poll_wait(&mut this.wait_key, cx)
+            }
+        }
+
+        impl Drop for WaitGroupFuture {
+            fn drop(&mut self) {
+                self.inner.cancel(&mut self.wait_key);
+            }
+        }
+
+        impl fmt::Debug for WaitGroupFuture {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("WaitGroupFuture")
+                    .field("count", &self.inner.count())
+                    .finish()
+            }
+        }
+
+        /// A latch counting down from a fixed count, which releases its waiters
+        /// when it reaches zero.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// # futures::executor::block_on(async {
+        #[doc = concat!("use synchrony::", stringify!($sync), "::wait_group::Latch;")]
+        /// let latch = Latch::new(2);
+        /// latch.count_down();
+        /// assert!(!latch.try_wait());
+        /// futures::join!(latch.wait(), async { latch.count_down() });
+        /// assert_eq!(latch.count(), 0);
+        /// # });
+        /// ```
+        pub struct Latch {
+            inner: Counter,
+        }
+
+        impl Latch {
+            /// Create a new latch that is released after `count` calls to
+            /// [`count_down`](Self::count_down).
+            pub fn new(count: usize) -> Self {
+                Self {
+                    inner: Counter::new(count),
+                }
+            }
+
+            /// Remaining count before the latch is released.
+            pub fn count(&self) -> usize {
+                self.inner.count()
+            }
+
+            /// Decrement the count, releasing all the waiters if it reaches zero.
+            ///
+            /// Does nothing if the latch has already been released.
+            pub fn count_down(&self) {
+                self.inner.count_down();
+            }
+
+            /// Returns whether the latch has been released.
+            pub fn try_wait(&self) -> bool {
+                self.count() == 0
+            }
+
+            /// Wait for the latch to be released.
+            pub fn wait(&self) -> LatchFuture<'_> {
+                LatchFuture {
+                    latch: self,
+                    wait_key: None,
+                }
+            }
+        }
+
+        impl fmt::Debug for Latch {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("Latch")
+                    .field("count", &self.count())
+                    .finish()
+            }
+        }
+
+        /// A future which resolves when a [`Latch`] is released.
+        pub struct LatchFuture<'a> {
+            latch: &'a Latch,
+            wait_key: Option<WakerKey>,
+        }
+
+        impl Future for LatchFuture<'_> {
+            type Output = ();
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+                let this = self.get_mut();
+                this.latch.inner.poll_wait(&mut this.wait_key, cx)
+            }
+        }
+
+        impl Drop for LatchFuture<'_> {
+            fn drop(&mut self) {
+                self.latch.inner.cancel(&mut self.wait_key);
+            }
+        }
+
+        impl fmt::Debug for LatchFuture<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("LatchFuture")
+                    .field("latch", self.latch)
+                    .finish()
+            }
+        }
+    };
+}
+
+use impl_wait_group;
@@ -1,20 +1,52 @@
-//! A slot holds up to one waker for task wakeup.
+//! A slot holds up to one waker for task wakeup, and a set holds any number of
+//! them.
 //!
-//! `sync` version is just [`futures_util::task::AtomicWaker`]; unsync version
-//! is a hand-rolled singlethreaded version with similar API.
+//! `sync` version of `WakerSlot` is just [`futures_util::task::AtomicWaker`];
+//! unsync version is a hand-rolled singlethreaded version with similar API.
 
 /// Multithreaded `WakerSlot` based on [`futures_util::task::AtomicWaker`].
 pub mod sync {
-    crate::cfg_loom! {
-        pub use futures_util::task::AtomicWaker as WakerSlot;
+    #[cfg(not(loom))]
+    pub use futures_util::task::AtomicWaker as WakerSlot;
+
+    /// loom's `AtomicWaker` with the API of
+    /// [`futures_util::task::AtomicWaker`].
+    #[cfg(loom)]
+    #[derive(Debug, Default)]
+    pub struct WakerSlot(loom::future::AtomicWaker);
+
+    #[cfg(loom)]
+    impl WakerSlot {
+        /// Create a new [`WakerSlot`]
+        pub fn new() -> Self {
+            Self(loom::future::AtomicWaker::new())
+        }
+
+        /// Register given waker
+        pub fn register(&self, waker: &core::task::Waker) {
+            self.0.register_by_ref(waker)
+        }
+
+        /// Try to take the stored waker
+        pub fn take(&self) -> Option<core::task::Waker> {
+            self.0.take_waker()
+        }
+
+        /// Wake currently stored waker
+        pub fn wake(&self) {
+            self.0.wake()
+        }
     }
 
+    super::impl_waker_set!(sync);
+
     impl crate::AssertMt for WakerSlot {}
+    impl crate::AssertMt for WakerSet {}
 }
 
 /// Singlethreaded `WakerSlot`
 pub mod unsync {
-    use std::{cell::RefCell, task::Waker};
+    use core::{cell::RefCell, task::Waker};
 
     /// A singlethreaded registry holds up to one waker for task wakeup.
     #[derive(Debug, Default)]
@@ -52,4 +84,259 @@ pub mod unsync {
             }
         }
     }
+    super::impl_waker_set!(unsync);
+}
+
+use alloc::vec::Vec;
+use core::{mem, task::Waker};
+
+use slab::Slab;
+
+// Sentinel for the ends of the queue.
+const NIL: usize = usize::MAX;
+
+enum Waiter {
+    Waiting(Waker),
+    Woken,
+}
+
+struct Entry {
+    waiter: Waiter,
+    prev: usize,
+    next: usize,
+}
+
+// A FIFO queue of waiters, linked through the entries of a `Slab`. Woken
+// waiters keep their place until they are removed, so that they can be woken
+// again in order if they register again.
+struct Queue {
+    entries: Slab<Entry>,
+    head: usize,
+    tail: usize,
+}
+
+impl Queue {
+    const fn new() -> Self {
+        Self {
+            entries: Slab::new(),
+            head: NIL,
+            tail: NIL,
+        }
+    }
+
+    fn insert(&mut self, waker: &Waker) -> usize {
+        let key = self.entries.insert(Entry {
+            waiter: Waiter::Waiting(waker.clone()),
+            prev: self.tail,
+            next: NIL,
+        });
+        match self.tail {
+            NIL => self.head = key,
+            tail => self.entries[tail].next = key,
+        }
+        self.tail = key;
+        key
+    }
+
+    // Update the waker of a waiter, returning the replaced one. Dropping it
+    // may reenter the set, so it's dropped once the queue is released.
+    fn update(&mut self, key: usize, waker: &Waker) -> Option<Waker> {
+        let waiter = &mut self.entries[key].waiter;
+        match waiter {
+            Waiter::Waiting(w) if waker.will_wake(w) => None,
+            _ => match mem::replace(waiter, Waiter::Waiting(waker.clone())) {
+                Waiter::Waiting(w) => Some(w),
+                Waiter::Woken => None,
+            },
+        }
+    }
+
+    // Unlink a waiter, returning it to drop once the queue is released, as in
+    // `update`.
+    fn remove(&mut self, key: usize) -> Waiter {
+        let entry = self.entries.remove(key);
+        match entry.prev {
+            NIL => self.head = entry.next,
+            prev => self.entries[prev].next = entry.next,
+        }
+        match entry.next {
+            NIL => self.tail = entry.prev,
+            next => self.entries[next].prev = entry.prev,
+        }
+        entry.waiter
+    }
+
+    fn wake_one(&mut self) -> Option<Waker> {
+        let mut key = self.head;
+        while key != NIL {
+            let entry = &mut self.entries[key];
+            if let Waiter::Waiting(_) = entry.waiter {
+                match mem::replace(&mut entry.waiter, Waiter::Woken) {
+                    Waiter::Waiting(waker) => return Some(waker),
+                    Waiter::Woken => unreachable!(),
+                }
+            }
+            key = entry.next;
+        }
+        None
+    }
+
+    fn wake_all(&mut self) -> Vec<Waker> {
+        let mut wakers = Vec::new();
+        let mut key = self.head;
+        while key != NIL {
+            let entry = &mut self.entries[key];
+            if let Waiter::Waiting(waker) = mem::replace(&mut entry.waiter, Waiter::Woken) {
+                wakers.push(waker);
+            }
+            key = entry.next;
+        }
+        wakers
+    }
 }
+
+macro_rules! impl_waker_set {
+    ($sync:ident) => {
+        use core::fmt;
+
+        use super::*;
+        use crate::$sync::mutex_blocking::RawMutex as Mutex;
+
+        /// Key of a waker registered in a [`WakerSet`].
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct WakerKey(usize);
+
+        /// A set of wakers, woken in FIFO order, for primitives with any number
+        /// of waiters.
+        ///
+        /// Each waiter [`insert`](Self::insert)s its waker on first poll,
+        /// [`update`](Self::update)s it on later polls, and
+        /// [`remove`](Self::remove)s it when it completes. A waker stays in the
+        /// set after being woken, so that a waiter which has to wait again keeps
+        /// its place in the queue. Wakers are always woken and dropped outside
+        /// of the internal lock.
+        ///
+        /// # Example
+        ///
+        /// ```
+        #[doc = concat!("use synchrony::", stringify!($sync), "::waker_slot::WakerSet;")]
+        /// let set = WakerSet::new();
+        /// let key = set.insert(futures::task::noop_waker_ref());
+        /// assert!(set.wake_one());
+        /// // Already woken, nothing left to wake.
+        /// assert!(!set.wake_one());
+        /// // Returns whether the waker has been woken.
+        /// assert!(set.remove(key));
+        /// assert!(set.is_empty());
+        /// ```
+        pub struct WakerSet {
+            queue: Mutex<Queue>,
+        }
+
+        impl WakerSet {
+            /// Create a new empty [`WakerSet`].
+            #[cfg(not(loom))]
+            pub const fn new() -> Self {
+                Self {
+                    queue: Mutex::new(Queue::new()),
+                }
+            }
+
+            /// Create a new empty [`WakerSet`].
+            ///
+            /// This `new` is not `const` due to loom not supporting it.
+            #[cfg(loom)]
+            pub fn new() -> Self {
+                Self {
+                    queue: Mutex::new(Queue::new()),
+                }
+            }
+
+            /// Register a new waiter at the back of the queue.
+            pub fn insert(&self, waker: &Waker) -> WakerKey {
+                WakerKey(self.queue.lock().insert(waker))
+            }
+
+            /// Update the waker of a waiter, marking it as waiting again if it has
+            /// been woken.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `key` has been removed.
+            pub fn update(&self, key: WakerKey, waker: &Waker) {
+                let replaced = self.queue.lock().update(key.0, waker);
+                drop(replaced);
+            }
+
+            /// Remove a waiter, returning whether it had been woken.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `key` has already been removed.
+            pub fn remove(&self, key: WakerKey) -> bool {
+                let waiter = self.queue.lock().remove(key.0);
+                matches!(waiter, Waiter::Woken)
+            }
+
+            /// Remove a waiter that gives up waiting, e.g. when its future is
+            /// dropped. If it had been woken, the wakeup is passed on to the next
+            /// waiter, so that it isn't lost.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `key` has already been removed.
+            pub fn cancel(&self, key: WakerKey) {
+                if self.remove(key) {
+                    self.wake_one();
+                }
+            }
+
+            /// Wake the first waiter that hasn't been woken yet, returning whether
+            /// there was one.
+            pub fn wake_one(&self) -> bool {
+                let waker = self.queue.lock().wake_one();
+                match waker {
+                    Some(waker) => {
+                        waker.wake();
+                        true
+                    }
+                    None => false,
+                }
+            }
+
+            /// Wake all the waiters that haven't been woken yet.
+            pub fn wake_all(&self) {
+                let wakers = self.queue.lock().wake_all();
+                for waker in wakers {
+                    waker.wake();
+                }
+            }
+
+            /// Number of registered waiters, woken or not.
+            pub fn len(&self) -> usize {
+                self.queue.lock().entries.len()
+            }
+
+            /// Returns whether there is no registered waiter.
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+        }
+
+        impl Default for WakerSet {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl fmt::Debug for WakerSet {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("WakerSet")
+                    .field("len", &self.len())
+                    .finish()
+            }
+        }
+    };
+}
+
+use impl_waker_set;
@@ -0,0 +1,533 @@
+//! A single-value channel, where receivers watch for the latest value, in the
+//! spirit of `tokio::sync::watch`.
+
+/// Multithreaded [`channel`](sync::channel)
+pub mod sync {
+    pub use super::{RecvError, SendError};
+
+    super::impl_watch!(sync);
+
+    impl<T: Send> crate::AssertMt for Sender<T> {}
+    impl<T: Send> crate::AssertMt for Receiver<T> {}
+}
+
+/// Singlethreaded [`channel`](unsync::channel)
+pub mod unsync {
+    pub use super::{RecvError, SendError};
+
+    super::impl_watch!(unsync);
+}
+
+use core::fmt;
+
+// The low bit of the state is set once all the senders are dropped, and the
+// rest is the version of the value.
+const CLOSED: usize = 1;
+const STEP: usize = 2;
+
+/// Error returned by `send` when there is no receiver, with the value that
+/// couldn't be sent.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("channel closed")
+    }
+}
+
+impl<T> core::error::Error for SendError<T> {}
+
+/// Error returned when waiting for a change after all the senders are dropped.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RecvError(());
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("channel closed")
+    }
+}
+
+impl core::error::Error for RecvError {}
+
+macro_rules! impl_watch {
+    ($sync:ident) => {
+        use core::{
+            fmt, mem,
+            ops::Deref,
+            sync::atomic::Ordering,
+            task::{Context, Poll},
+        };
+
+        use super::{CLOSED, STEP};
+        use crate::$sync::{
+            atomic::AtomicUsize,
+            mutex_blocking::{Mutex, MutexGuard},
+            shared::Shared,
+            waker_slot::{WakerKey, WakerSet},
+        };
+
+        struct Inner<T> {
+            value: Mutex<T>,
+            state: AtomicUsize,
+            senders: AtomicUsize,
+            receivers: AtomicUsize,
+            // Receivers waiting for a change.
+            changed: WakerSet,
+            // Senders waiting for all the receivers to be dropped.
+            closed: WakerSet,
+        }
+
+        impl<T> Inner<T> {
+            fn version(&self) -> usize {
+                self.state.load(Ordering::Acquire) & !CLOSED
+            }
+
+            fn is_closed(&self) -> bool {
+                self.state.load(Ordering::Acquire) & CLOSED != 0
+            }
+
+            // Wait for the version to differ from `seen`, and mark it as seen.
+            async fn changed(&self, seen: &mut usize) -> Result<(), RecvError> {
+                let mut waiter = Waiter::new(&self.changed);
+                core::future::poll_fn(|cx| {
+                    waiter.poll(cx, || self.version() != *seen || self.is_closed())
+                })
+                .await;
+                let version = self.version();
+                if version == *seen {
+                    return Err(RecvError(()));
+                }
+                *seen = version;
+                Ok(())
+            }
+        }
+
+        // A registration in a `WakerSet`, removed when dropped.
+        struct Waiter<'a> {
+            waiters: &'a WakerSet,
+            key: Option<WakerKey>,
+        }
+
+        impl<'a> Waiter<'a> {
+            fn new(waiters: &'a WakerSet) -> Self {
+                Self { waiters, key: None }
+            }
+
+            fn poll(&mut self, cx: &mut Context<'_>, ready: impl Fn() -> bool) -> Poll<()> {
+                if !ready() {
+                    match self.key {
+                        Some(key) => self.waiters.update(key, cx.waker()),
+                        None => self.key = Some(self.waiters.insert(cx.waker())),
+                    }
+                    // Check again after registering, so that a wakeup racing with
+                    // the registration isn't missed.
+                    if !ready() {
+                        return Poll::Pending;
+                    }
+                }
+                Poll::Ready(())
+            }
+        }
+
+        impl Drop for Waiter<'_> {
+            fn drop(&mut self) {
+                if let Some(key) = self.key.take() {
+                    self.waiters.remove(key);
+                }
+            }
+        }
+
+        /// Create a channel holding `init`, returning its first sender and
+        /// receiver.
+        ///
+        /// # Example
+        ///
+        /// ```
+        /// # futures::executor::block_on(async {
+        #[doc = concat!("use synchrony::", stringify!($sync), "::watch::channel;")]
+        /// let (tx, mut rx) = channel("hello");
+        /// futures::join!(
+        ///     async {
+        ///         rx.changed().await.unwrap();
+        ///         assert_eq!(*rx.borrow_and_update(), "world");
+        ///     },
+        ///     async { tx.send("world").unwrap() },
+        /// );
+        /// # });
+        /// ```
+        pub fn channel<T>(init: T) -> (Sender<T>, Receiver<T>) {
+            let tx = Sender::new(init);
+            let rx = tx.subscribe();
+            (tx, rx)
+        }
+
+        /// The sending half of a [`channel`], which can be cloned.
+        pub struct Sender<T> {
+            inner: Shared<Inner<T>>,
+        }
+
+        impl<T> Sender<T> {
+            /// Create a new channel holding `init`, with no receiver yet.
+            pub fn new(init: T) -> Self {
+                Self {
+                    inner: Shared::new(Inner {
+                        value: Mutex::new(init),
+                        state: AtomicUsize::new(0),
+                        senders: AtomicUsize::new(1),
+                        receivers: AtomicUsize::new(0),
+                        changed: WakerSet::new(),
+                        closed: WakerSet::new(),
+                    }),
+                }
+            }
+
+            /// Send a new value, notifying all the receivers.
+            ///
+            /// Fails if there is no receiver, returning the value.
+            pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+                if self.is_closed() {
+                    return Err(SendError(value));
+                }
+                self.send_replace(value);
+                Ok(())
+            }
+
+            /// Send a new value even if there is no receiver, returning the
+            /// previous one.
+            pub fn send_replace(&self, value: T) -> T {
+                let mut previous = None;
+                self.send_modify(|current| previous = Some(mem::replace(current, value)));
+                previous.expect("`send_modify` calls `modify`")
+            }
+
+            /// Modify the value in place, notifying all the receivers.
+            pub fn send_modify(&self, modify: impl FnOnce(&mut T)) {
+                self.send_if_modified(|value| {
+                    modify(value);
+                    true
+                });
+            }
+
+            /// Modify the value in place, notifying all the receivers only if
+            /// `modify` returns `true`, and returning it.
+            ///
+            /// # Example
+            ///
+            /// ```
+            #[doc = concat!("use synchrony::", stringify!($sync), "::watch::channel;")]
+            /// let (tx, rx) = channel(1);
+            /// assert!(!tx.send_if_modified(|value| false));
+            /// assert!(!rx.has_changed().unwrap());
+            /// assert!(tx.send_if_modified(|value| {
+            ///     *value += 1;
+            ///     true
+            /// }));
+            /// assert!(rx.has_changed().unwrap());
+            /// ```
+            pub fn send_if_modified(&self, modify: impl FnOnce(&mut T) -> bool) -> bool {
+                let mut value = self.inner.value.lock();
+                if !modify(&mut value) {
+                    return false;
+                }
+                // Bump the version before unlocking, so that it matches the value
+                // under the lock.
+                self.inner.state.fetch_add(STEP, Ordering::AcqRel);
+                drop(value);
+                self.inner.changed.wake_all();
+                true
+            }
+
+            /// Borrow the current value.
+            ///
+            /// # Deadlocks
+            ///
+            /// The value stays locked while the [`Guard`] is alive, see
+            /// [its deadlocks](Guard#deadlocks).
+            pub fn borrow(&self) -> Guard<'_, T> {
+                Guard {
+                    value: self.inner.value.lock(),
+                    has_changed: false,
+                }
+            }
+
+            /// Create a new receiver, which has seen the current value.
+            pub fn subscribe(&self) -> Receiver<T> {
+                let version = self.inner.version();
+                Receiver::new(self.inner.clone(), version)
+            }
+
+            /// Number of receivers of the channel.
+            pub fn receiver_count(&self) -> usize {
+                self.inner.receivers.load(Ordering::Acquire)
+            }
+
+            /// Number of senders of the channel.
+            pub fn sender_count(&self) -> usize {
+                self.inner.senders.load(Ordering::Acquire)
+            }
+
+            /// Returns whether there is no receiver.
+            pub fn is_closed(&self) -> bool {
+                self.receiver_count() == 0
+            }
+
+            /// Wait for all the receivers to be dropped.
+            ///
+            /// # Example
+            ///
+            /// ```
+            /// # futures::executor::block_on(async {
+            #[doc = concat!("use synchrony::", stringify!($sync), "::watch::channel;")]
+            /// let (tx, rx) = channel(());
+            /// let rx2 = tx.subscribe();
+            /// assert_eq!(tx.receiver_count(), 2);
+            /// futures::join!(tx.closed(), async move { drop((rx, rx2)) });
+            /// assert!(tx.is_closed());
+            /// # });
+            /// ```
+            pub async fn closed(&self) {
+                let mut waiter = Waiter::new(&self.inner.closed);
+                core::future::poll_fn(|cx| waiter.poll(cx, || self.is_closed())).await
+            }
+
+            /// Returns whether both senders belong to the same channel.
+            pub fn same_channel(&self, other: &Self) -> bool {
+                Shared::ptr_eq(&self.inner, &other.inner)
+            }
+        }
+
+        impl<T: Default> Default for Sender<T> {
+            fn default() -> Self {
+                Self::new(T::default())
+            }
+        }
+
+        impl<T> Clone for Sender<T> {
+            fn clone(&self) -> Self {
+                self.inner.senders.fetch_add(1, Ordering::Relaxed);
+                Self {
+                    inner: self.inner.clone(),
+                }
+            }
+        }
+
+        impl<T> Drop for Sender<T> {
+            fn drop(&mut self) {
+                if self.inner.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+                    self.inner.state.fetch_or(CLOSED, Ordering::AcqRel);
+                    self.inner.changed.wake_all();
+                }
+            }
+        }
+
+        impl<T> fmt::Debug for Sender<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("Sender")
+                    .field("version", &self.inner.version())
+                    .field("receivers", &self.receiver_count())
+                    .finish()
+            }
+        }
+
+        /// The receiving half of a [`channel`], which can be cloned.
+        ///
+        /// Each receiver keeps track of the last version of the value it has
+        /// seen.
+        pub struct Receiver<T> {
+            inner: Shared<Inner<T>>,
+            version: usize,
+        }
+
+        impl<T> Receiver<T> {
+            fn new(inner: Shared<Inner<T>>, version: usize) -> Self {
+                inner.receivers.fetch_add(1, Ordering::Relaxed);
+                Self { inner, version }
+            }
+
+            /// Borrow the current value, without marking it as seen.
+            ///
+            /// # Deadlocks
+            ///
+            /// The value stays locked while the [`Guard`] is alive, see
+            /// [its deadlocks](Guard#deadlocks).
+            pub fn borrow(&self) -> Guard<'_, T> {
+                let value = self.inner.value.lock();
+                Guard {
+                    value,
+                    has_changed: self.inner.version() != self.version,
+                }
+            }
+
+            /// Borrow the current value, and mark it as seen.
+            ///
+            /// # Deadlocks
+            ///
+            /// The value stays locked while the [`Guard`] is alive, see
+            /// [its deadlocks](Guard#deadlocks).
+            pub fn borrow_and_update(&mut self) -> Guard<'_, T> {
+                let value = self.inner.value.lock();
+                let version = self.inner.version();
+                let has_changed = version != self.version;
+                self.version = version;
+                Guard { value, has_changed }
+            }
+
+            /// Returns whether the value has changed since it was last seen.
+            ///
+            /// Fails if all the senders are dropped and there is no unseen
+            /// change.
+            pub fn has_changed(&self) -> Result<bool, RecvError> {
+                if self.inner.version() != self.version {
+                    Ok(true)
+                } else if self.inner.is_closed() {
+                    Err(RecvError(()))
+                } else {
+                    Ok(false)
+                }
+            }
+
+            /// Mark the current value as unseen.
+            pub fn mark_changed(&mut self) {
+                self.version = self.inner.version().wrapping_sub(STEP);
+            }
+
+            /// Mark the current value as seen.
+            pub fn mark_unchanged(&mut self) {
+                self.version = self.inner.version();
+            }
+
+            /// Wait for a value that hasn't been seen yet, and mark it as seen.
+            ///
+            /// Fails once all the senders are dropped and there is no unseen
+            /// change.
+            pub async fn changed(&mut self) -> Result<(), RecvError> {
+                self.inner.changed(&mut self.version).await
+            }
+
+            /// Wait for a value that satisfies `predicate`, starting with the
+            /// current one, and mark it as seen.
+            ///
+            /// Fails once all the senders are dropped and no value satisfied
+            /// `predicate`.
+            ///
+            /// # Deadlocks
+            ///
+            /// The value is locked while `predicate` runs, and stays locked while
+            /// the returned [`Guard`] is alive, see [its deadlocks](Guard#deadlocks).
+            ///
+            /// # Example
+            ///
+            /// ```
+            /// # futures::executor::block_on(async {
+            #[doc = concat!("use synchrony::", stringify!($sync), "::watch::channel;")]
+            /// let (tx, mut rx) = channel(0);
+            /// futures::join!(
+            ///     async {
+            ///         let value = rx.wait_for(|value| *value >= 3).await.unwrap();
+            ///         assert_eq!(*value, 3);
+            ///     },
+            ///     async {
+            ///         for i in 1..=3 {
+            ///             tx.send(i).unwrap();
+            ///         }
+            ///     },
+            /// );
+            /// # });
+            /// ```
+            pub async fn wait_for(
+                &mut self,
+                mut predicate: impl FnMut(&T) -> bool,
+            ) -> Result<Guard<'_, T>, RecvError> {
+                loop {
+                    let value = self.inner.value.lock();
+                    let version = self.inner.version();
+                    let has_changed = version != self.version;
+                    self.version = version;
+                    if predicate(&value) {
+                        return Ok(Guard { value, has_changed });
+                    }
+                    drop(value);
+                    self.inner.changed(&mut self.version).await?;
+                }
+            }
+
+            /// Returns whether both receivers belong to the same channel.
+            pub fn same_channel(&self, other: &Self) -> bool {
+                Shared::ptr_eq(&self.inner, &other.inner)
+            }
+        }
+
+        impl<T> Clone for Receiver<T> {
+            fn clone(&self) -> Self {
+                Self::new(self.inner.clone(), self.version)
+            }
+        }
+
+        impl<T> Drop for Receiver<T> {
+            fn drop(&mut self) {
+                if self.inner.receivers.fetch_sub(1, Ordering::AcqRel) == 1 {
+                    self.inner.closed.wake_all();
+                }
+            }
+        }
+
+        impl<T> fmt::Debug for Receiver<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("Receiver")
+                    .field("version", &self.version)
+                    .field("closed", &self.inner.is_closed())
+                    .finish()
+            }
+        }
+
+        /// A borrowed value of a [`channel`], which keeps it locked while it is
+        /// alive.
+        ///
+        /// # Deadlocks
+        ///
+        /// The value is behind a blocking mutex, so while a guard is alive, on
+        /// the same thread:
+        ///
+        /// - borrowing the value again, through any sender or receiver,
+        /// - or sending a value, through any sender,
+        ///
+        /// deadlocks with the multithreaded flavor, and panics with the
+        /// singlethreaded one. Other threads block until the guard is dropped,
+        /// so don't hold it across an `.await`.
+        pub struct Guard<'a, T> {
+            value: MutexGuard<'a, T>,
+            has_changed: bool,
+        }
+
+        impl<T> Guard<'_, T> {
+            /// Returns whether the value hadn't been seen by the receiver when it
+            /// was borrowed. Always `false` for [`Sender::borrow`].
+            pub fn has_changed(&self) -> bool {
+                self.has_changed
+            }
+        }
+
+        impl<T> Deref for Guard<'_, T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                &self.value
+            }
+        }
+
+        impl<T: fmt::Debug> fmt::Debug for Guard<'_, T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                (**self).fmt(f)
+            }
+        }
+    };
+}
+
+use impl_watch;
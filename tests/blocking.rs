@@ -0,0 +1,34 @@
+//! Run with `cargo test --features mutex`.
+#![cfg(all(feature = "std", feature = "mutex", not(loom)))]
+
+use std::{sync::Arc, thread, time::Duration};
+
+use futures::executor::block_on;
+use synchrony::sync::mutex::Mutex;
+
+#[test]
+fn waits_for_an_async_holder() {
+    let mutex = Arc::new(Mutex::new(0));
+    let guard = block_on(mutex.lock());
+    let handle = thread::spawn({
+        let mutex = mutex.clone();
+        move || *mutex.blocking_lock() += 1
+    });
+    thread::sleep(Duration::from_millis(10));
+    assert!(!handle.is_finished());
+    drop(guard);
+    handle.join().unwrap();
+    assert_eq!(*mutex.try_lock().unwrap(), 1);
+}
+
+// Async contexts can't be detected, so this doesn't panic, and only blocks
+// the executor thread if the lock is held.
+#[test]
+fn is_not_detected_in_async_contexts() {
+    let mutex = Mutex::new(0);
+    block_on(async {
+        *mutex.blocking_lock() += 1;
+        *mutex.lock().await += 1;
+    });
+    assert_eq!(*mutex.try_lock().unwrap(), 2);
+}
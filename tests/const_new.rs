@@ -0,0 +1,168 @@
+//! Every primitive that doesn't allocate can be created in a `static`.
+#![cfg(not(loom))]
+
+#[cfg(feature = "rate_limit")]
+use std::{
+    future::{Ready, ready},
+    time::Duration,
+};
+
+#[cfg(feature = "rate_limit")]
+struct ZeroClock;
+
+#[cfg(feature = "rate_limit")]
+impl synchrony::sync::rate_limit::Clock for ZeroClock {
+    type Sleep = Ready<()>;
+
+    fn now(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn sleep(&self, _: Duration) -> Ready<()> {
+        ready(())
+    }
+}
+
+mod sync {
+    use synchrony::sync::*;
+
+    static ATOMIC: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
+    static FLAG: flag::Flag = flag::Flag::new(false);
+    static MUTEX_BLOCKING: mutex_blocking::Mutex<Vec<u8>> = mutex_blocking::Mutex::new(Vec::new());
+    #[cfg(feature = "std")]
+    static REENTRANT: mutex_blocking::ReentrantMutex<u8> = mutex_blocking::ReentrantMutex::new(0);
+    static SPIN: spin::SpinMutex<u8> = spin::SpinMutex::new(0);
+    static SEQLOCK: seqlock::SeqLock<u8> = seqlock::SeqLock::new(0);
+    static ONCE: once::OnceLock<u8> = once::OnceLock::new();
+    static LAZY: once::Lazy<u8> = once::Lazy::new(|| 1);
+    #[cfg(feature = "std")]
+    static PER_THREAD: per_thread::PerThread<u8> = per_thread::PerThread::new();
+    #[cfg(feature = "waker_slot")]
+    static WAKER_SLOT: waker_slot::WakerSlot = waker_slot::WakerSlot::new();
+    #[cfg(feature = "waker_slot")]
+    static WAKER_SET: waker_slot::WakerSet = waker_slot::WakerSet::new();
+    #[cfg(feature = "mutex")]
+    static MUTEX: mutex::Mutex<u8> = mutex::Mutex::new(0);
+    #[cfg(feature = "async_flag")]
+    static ASYNC_FLAG: async_flag::AsyncFlag = async_flag::AsyncFlag::new();
+    #[cfg(feature = "reset_event")]
+    static MANUAL_RESET: reset_event::ManualResetEvent = reset_event::ManualResetEvent::new(false);
+    #[cfg(feature = "reset_event")]
+    static AUTO_RESET: reset_event::AutoResetEvent = reset_event::AutoResetEvent::new(false);
+    #[cfg(feature = "rate_limit")]
+    static RATE_LIMIT: rate_limit::RateLimiter<super::ZeroClock> =
+        rate_limit::RateLimiter::new(super::ZeroClock, 1, super::Duration::from_secs(1), 1);
+
+    #[test]
+    fn statics() {
+        use std::sync::atomic::Ordering;
+
+        ATOMIC.fetch_add(1, Ordering::Relaxed);
+        FLAG.flip();
+        MUTEX_BLOCKING.lock().push(1);
+        #[cfg(feature = "std")]
+        assert_eq!(*REENTRANT.lock(), 0);
+        assert_eq!(*SPIN.lock(), 0);
+        assert_eq!(SEQLOCK.read(), 0);
+        ONCE.get_or_init(|| 1);
+        assert_eq!(*LAZY, 1);
+        #[cfg(feature = "std")]
+        PER_THREAD.get_or(|| 1);
+        #[cfg(feature = "waker_slot")]
+        {
+            WAKER_SLOT.wake();
+            WAKER_SET.wake_all();
+        }
+        #[cfg(feature = "mutex")]
+        drop(MUTEX.try_lock().unwrap());
+        #[cfg(feature = "async_flag")]
+        ASYNC_FLAG.handle().notify();
+        #[cfg(feature = "reset_event")]
+        {
+            MANUAL_RESET.set();
+            AUTO_RESET.set();
+        }
+        #[cfg(feature = "rate_limit")]
+        assert!(RATE_LIMIT.try_acquire(1));
+    }
+}
+
+mod unsync {
+    use synchrony::unsync::*;
+
+    thread_local! {
+        static ATOMIC: atomic::AtomicUsize = const { atomic::AtomicUsize::new(0) };
+        static FLAG: flag::Flag = const { flag::Flag::new(false) };
+        static MUTEX_BLOCKING: mutex_blocking::Mutex<Vec<u8>> =
+            const { mutex_blocking::Mutex::new(Vec::new()) };
+        static REENTRANT: mutex_blocking::ReentrantMutex<u8> =
+            const { mutex_blocking::ReentrantMutex::new(0) };
+        static SPIN: spin::SpinMutex<u8> = const { spin::SpinMutex::new(0) };
+        static SEQLOCK: seqlock::SeqLock<u8> = const { seqlock::SeqLock::new(0) };
+        static ONCE: once::OnceLock<u8> = const { once::OnceLock::new() };
+        static LAZY: once::Lazy<u8> = const { once::Lazy::new(|| 1) };
+        static PER_THREAD: per_thread::PerThread<u8> = const { per_thread::PerThread::new() };
+    }
+
+    #[cfg(feature = "waker_slot")]
+    thread_local! {
+        static WAKER_SLOT: waker_slot::WakerSlot = const { waker_slot::WakerSlot::new() };
+        static WAKER_SET: waker_slot::WakerSet = const { waker_slot::WakerSet::new() };
+    }
+
+    #[cfg(feature = "mutex")]
+    thread_local! {
+        static MUTEX: mutex::Mutex<u8> = const { mutex::Mutex::new(0) };
+    }
+
+    #[cfg(feature = "async_flag")]
+    thread_local! {
+        static ASYNC_FLAG: async_flag::AsyncFlag = const { async_flag::AsyncFlag::new() };
+    }
+
+    #[cfg(feature = "reset_event")]
+    thread_local! {
+        static MANUAL_RESET: reset_event::ManualResetEvent =
+            const { reset_event::ManualResetEvent::new(false) };
+        static AUTO_RESET: reset_event::AutoResetEvent =
+            const { reset_event::AutoResetEvent::new(false) };
+    }
+
+    #[cfg(feature = "rate_limit")]
+    thread_local! {
+        static RATE_LIMIT: rate_limit::RateLimiter<super::ZeroClock> = const {
+            rate_limit::RateLimiter::new(super::ZeroClock, 1, super::Duration::from_secs(1), 1)
+        };
+    }
+
+    #[test]
+    fn statics() {
+        use std::sync::atomic::Ordering;
+
+        ATOMIC.with(|v| v.fetch_add(1, Ordering::Relaxed));
+        FLAG.with(|v| v.flip());
+        MUTEX_BLOCKING.with(|v| v.lock().push(1));
+        REENTRANT.with(|v| *v.lock());
+        SPIN.with(|v| *v.lock());
+        SEQLOCK.with(|v| v.read());
+        ONCE.with(|v| *v.get_or_init(|| 1));
+        assert_eq!(LAZY.with(|v| **v), 1);
+        PER_THREAD.with(|v| *v.get_or(|| 1));
+        #[cfg(feature = "waker_slot")]
+        {
+            WAKER_SLOT.with(|v| v.wake());
+            WAKER_SET.with(|v| v.wake_all());
+        }
+        #[cfg(feature = "mutex")]
+        MUTEX.with(|v| drop(v.try_lock().unwrap()));
+        #[cfg(feature = "async_flag")]
+        ASYNC_FLAG.with(|v| v.handle().notify());
+        #[cfg(feature = "reset_event")]
+        {
+            MANUAL_RESET.with(|v| v.set());
+            AUTO_RESET.with(|v| v.set());
+        }
+        #[cfg(feature = "rate_limit")]
+        assert!(RATE_LIMIT.with(|v| v.try_acquire(1)));
+    }
+}
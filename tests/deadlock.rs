@@ -0,0 +1,37 @@
+//! Run with `cargo test --features deadlock-detection,mutex`.
+#![cfg(all(feature = "deadlock-detection", feature = "mutex", not(loom)))]
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use futures::executor::block_on;
+use synchrony::{deadlock, sync::mutex::Mutex};
+
+// The hook is global, so everything runs in a single test.
+#[test]
+fn try_lock_and_lock_share_an_owner() {
+    let found = Arc::new(AtomicUsize::new(0));
+    deadlock::set_hook({
+        let found = found.clone();
+        move |_| {
+            found.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+
+    let a = Mutex::new(());
+    let b = Mutex::new(());
+    block_on(async {
+        {
+            let _a = a.try_lock().unwrap();
+            let _b = b.lock().await;
+        }
+        assert_eq!(found.load(Ordering::Relaxed), 0);
+        {
+            let _b = b.try_lock().unwrap();
+            let _a = a.lock().await;
+        }
+    });
+    assert_eq!(found.load(Ordering::Relaxed), 1);
+}
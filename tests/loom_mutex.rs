@@ -0,0 +1,143 @@
+//! Run with `RUSTFLAGS="--cfg loom" cargo test --release --features mutex`.
+#![cfg(all(loom, feature = "mutex"))]
+
+use std::{
+    future::Future,
+    task::{Context, Poll},
+};
+
+use futures::task::noop_waker_ref;
+use loom::{future::block_on, sync::Arc, thread};
+use synchrony::sync::mutex::{Mutex, MutexGuard, lock_all};
+
+#[test]
+fn mutual_exclusion() {
+    loom::model(|| {
+        let mutex = Arc::new(Mutex::new(0));
+        let handles = (0..2)
+            .map(|_| {
+                let mutex = mutex.clone();
+                thread::spawn(move || block_on(async { *mutex.lock().await += 1 }))
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(*mutex.try_lock().unwrap(), 2);
+    });
+}
+
+#[test]
+fn waiters_are_woken() {
+    // Three threads are too many for an exhaustive search.
+    let mut builder = loom::model::Builder::new();
+    builder.preemption_bound = Some(2);
+    builder.check(|| {
+        let mutex = Arc::new(Mutex::new(0));
+        let guard = mutex.try_lock().unwrap();
+        let handles = (0..2)
+            .map(|_| {
+                let mutex = mutex.clone();
+                thread::spawn(move || block_on(async { *mutex.lock().await += 1 }))
+            })
+            .collect::<Vec<_>>();
+        drop(guard);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(*mutex.try_lock().unwrap(), 2);
+    });
+}
+
+#[test]
+fn cancelled_waiter_passes_wakeup_on() {
+    loom::model(|| {
+        let mutex = Arc::new(Mutex::new(0));
+        let guard = mutex.try_lock().unwrap();
+        let handle = thread::spawn({
+            let mutex = mutex.clone();
+            move || block_on(async { *mutex.lock().await += 1 })
+        });
+
+        let mut cancelled = Box::pin(mutex.lock());
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+        // Wakes whichever waiter came first. If it's the cancelled one, it
+        // must pass the wakeup on when dropped.
+        drop(guard);
+        drop(cancelled);
+
+        handle.join().unwrap();
+        assert_eq!(*mutex.try_lock().unwrap(), 1);
+    });
+}
+
+#[test]
+fn cancelled_waiter_while_locked() {
+    loom::model(|| {
+        let mutex = Arc::new(Mutex::new(0));
+        let handle = thread::spawn({
+            let mutex = mutex.clone();
+            move || block_on(async { *mutex.lock().await += 1 })
+        });
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut cancelled = Box::pin(mutex.lock());
+        if let Poll::Ready(mut guard) = cancelled.as_mut().poll(&mut cx) {
+            *guard += 1;
+        }
+        drop(cancelled);
+
+        handle.join().unwrap();
+        let value = *mutex.try_lock().unwrap();
+        assert!(value == 1 || value == 2);
+    });
+}
+
+#[test]
+fn bump_races_a_waiter() {
+    loom::model(|| {
+        let mutex = Arc::new(Mutex::new(0));
+        let mut guard = mutex.try_lock().unwrap();
+        let handle = thread::spawn({
+            let mutex = mutex.clone();
+            move || block_on(async { *mutex.lock().await += 1 })
+        });
+
+        // Either the waiter is queued and gets the lock handed over, or the
+        // lock is released for it.
+        block_on(MutexGuard::bump(&mut guard));
+        *guard += 1;
+        drop(guard);
+
+        handle.join().unwrap();
+        assert_eq!(*mutex.try_lock().unwrap(), 2);
+    });
+}
+
+#[test]
+fn lock_all_in_opposite_orders() {
+    loom::model(|| {
+        let a = Arc::new(Mutex::new(0));
+        let b = Arc::new(Mutex::new(0));
+        let handle = thread::spawn({
+            let (a, b) = (a.clone(), b.clone());
+            move || {
+                block_on(async {
+                    let (mut a, mut b) = lock_all((&*a, &*b)).await;
+                    *a += 1;
+                    *b -= 1;
+                })
+            }
+        });
+        block_on(async {
+            let (mut b, mut a) = lock_all((&*b, &*a)).await;
+            *b += 1;
+            *a -= 1;
+        });
+
+        handle.join().unwrap();
+        assert_eq!(*a.try_lock().unwrap(), 0);
+        assert_eq!(*b.try_lock().unwrap(), 0);
+    });
+}
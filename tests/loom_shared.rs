@@ -0,0 +1,36 @@
+//! Run with `RUSTFLAGS="--cfg loom" cargo test --release`.
+#![cfg(loom)]
+
+use loom::{sync::Arc, thread};
+use synchrony::sync::shared::{Shared, SharedSwap};
+
+#[test]
+fn guard_outlives_store() {
+    loom::model(|| {
+        let swap = Arc::new(SharedSwap::from_pointee(1));
+        let handle = thread::spawn({
+            let swap = swap.clone();
+            move || {
+                let guard = swap.load_guard();
+                assert!(*guard == 1 || *guard == 2);
+            }
+        });
+        swap.store(Shared::new(2));
+        handle.join().unwrap();
+        assert_eq!(*swap.load_guard(), 2);
+    });
+}
+
+#[test]
+fn store_while_holding_guard() {
+    loom::model(|| {
+        let swap = SharedSwap::from_pointee(1);
+        let old = swap.load();
+        let guard = swap.load_guard();
+        swap.store(Shared::new(2));
+        assert_eq!(*guard, 1);
+        drop(guard);
+        // Only `old` is left, so the guard gave its reference back.
+        assert_eq!(Shared::strong_count(&old), 1);
+    });
+}
@@ -0,0 +1,308 @@
+//! Run with `cargo test --features mutex`.
+#![cfg(all(feature = "mutex", not(loom)))]
+
+use std::{
+    future::Future,
+    pin::pin,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll, Waker},
+};
+
+use futures::task::{ArcWake, noop_waker_ref, waker};
+use synchrony::sync;
+
+macro_rules! bump_hands_the_lock_over {
+    ($sync:ident) => {
+        #[test]
+        fn $sync() {
+            use synchrony::$sync::mutex::{Mutex, MutexGuard};
+
+            let mut cx = Context::from_waker(noop_waker_ref());
+            let mutex = Mutex::new(0);
+            let mut guard = mutex.try_lock().unwrap();
+            let mut waiter = pin!(mutex.lock());
+            assert!(waiter.as_mut().poll(&mut cx).is_pending());
+
+            {
+                // However often the bumping task runs, it waits for the waiter.
+                let mut bump = pin!(MutexGuard::bump(&mut guard));
+                assert!(bump.as_mut().poll(&mut cx).is_pending());
+                assert!(bump.as_mut().poll(&mut cx).is_pending());
+                assert!(mutex.try_lock().is_none());
+
+                let Poll::Ready(mut held) = waiter.poll(&mut cx) else {
+                    panic!("the lock was handed over");
+                };
+                *held += 1;
+                assert!(bump.as_mut().poll(&mut cx).is_pending());
+                drop(held);
+                assert!(bump.poll(&mut cx).is_ready());
+            }
+            assert_eq!(*guard, 1);
+        }
+    };
+}
+
+mod bump_hands_the_lock_over {
+    use super::*;
+
+    bump_hands_the_lock_over!(sync);
+    bump_hands_the_lock_over!(unsync);
+}
+
+macro_rules! cancelled_handoff_passes_the_lock_on {
+    ($sync:ident) => {
+        #[test]
+        fn $sync() {
+            use synchrony::$sync::mutex::{Mutex, MutexGuard};
+
+            let mut cx = Context::from_waker(noop_waker_ref());
+            let mutex = Mutex::new(());
+            let mut guard = mutex.try_lock().unwrap();
+            let mut first = Box::pin(mutex.lock());
+            let mut second = pin!(mutex.lock());
+            assert!(first.as_mut().poll(&mut cx).is_pending());
+            assert!(second.as_mut().poll(&mut cx).is_pending());
+
+            let mut bump = pin!(MutexGuard::bump(&mut guard));
+            assert!(bump.as_mut().poll(&mut cx).is_pending());
+            // Dropped while holding the lock, so it goes to the next one.
+            drop(first);
+            assert!(bump.as_mut().poll(&mut cx).is_pending());
+            assert!(second.poll(&mut cx).is_ready());
+            assert!(bump.poll(&mut cx).is_ready());
+        }
+    };
+}
+
+mod cancelled_handoff_passes_the_lock_on {
+    use super::*;
+
+    cancelled_handoff_passes_the_lock_on!(sync);
+    cancelled_handoff_passes_the_lock_on!(unsync);
+}
+
+// A waker which queues up on a mutex when dropped.
+struct Reenter(Arc<sync::mutex::Mutex<()>>);
+
+impl ArcWake for Reenter {
+    fn wake_by_ref(_: &Arc<Self>) {}
+}
+
+impl Drop for Reenter {
+    fn drop(&mut self) {
+        let mut lock = pin!(self.0.lock());
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert!(lock.as_mut().poll(&mut cx).is_pending());
+    }
+}
+
+#[test]
+fn wakers_are_dropped_outside_the_queue() {
+    let mutex = Arc::new(sync::mutex::Mutex::new(()));
+    let _guard = mutex.try_lock().unwrap();
+    let mut noop = Context::from_waker(noop_waker_ref());
+
+    // Replaced by another waker.
+    let mut waiter = Box::pin(mutex.lock());
+    let reenter = waker(Arc::new(Reenter(mutex.clone())));
+    assert!(
+        waiter
+            .as_mut()
+            .poll(&mut Context::from_waker(&reenter))
+            .is_pending()
+    );
+    drop(reenter);
+    assert!(waiter.as_mut().poll(&mut noop).is_pending());
+
+    // Removed from the queue.
+    let reenter = waker(Arc::new(Reenter(mutex.clone())));
+    assert!(
+        waiter
+            .as_mut()
+            .poll(&mut Context::from_waker(&reenter))
+            .is_pending()
+    );
+    drop(reenter);
+    drop(waiter);
+}
+
+fn assert_unpin<T: Unpin>() {}
+
+// Lock futures can be polled without pinning them first.
+#[test]
+fn lock_futures_are_unpin() {
+    assert_unpin::<sync::mutex::MutexLockFuture<'static, ()>>();
+    assert_unpin::<sync::mutex::OwnedMutexLockFuture<()>>();
+    assert_unpin::<synchrony::unsync::mutex::MutexLockFuture<'static, ()>>();
+    assert_unpin::<synchrony::unsync::mutex::OwnedMutexLockFuture<()>>();
+}
+
+#[derive(Default)]
+struct CountWakes(AtomicUsize);
+
+impl ArcWake for CountWakes {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// A waker for each waiter, to tell which ones have been woken.
+fn wakers<const N: usize>() -> ([Arc<CountWakes>; N], [Waker; N]) {
+    let counts: [Arc<CountWakes>; N] = std::array::from_fn(|_| Arc::default());
+    let wakers = counts.each_ref().map(|count| waker(count.clone()));
+    (counts, wakers)
+}
+
+fn woken<const N: usize>(counts: &[Arc<CountWakes>; N]) -> [usize; N] {
+    counts
+        .each_ref()
+        .map(|count| count.0.load(Ordering::Relaxed))
+}
+
+macro_rules! highest_priority_is_woken_first {
+    ($sync:ident) => {
+        #[test]
+        fn $sync() {
+            use synchrony::$sync::mutex::Mutex;
+
+            let mutex = Mutex::new(Vec::new());
+            let guard = mutex.try_lock().unwrap();
+            let (counts, wakers) = wakers::<4>();
+            let mut waiters =
+                [1, 5, 5, 1].map(|priority| Box::pin(mutex.lock_with_priority(priority)));
+            for (waiter, waker) in waiters.iter_mut().zip(&wakers) {
+                assert!(
+                    waiter
+                        .as_mut()
+                        .poll(&mut Context::from_waker(waker))
+                        .is_pending()
+                );
+            }
+
+            // The highest priority first, then in FIFO order among equals.
+            drop(guard);
+            for (i, expected) in [
+                (1, [0, 1, 0, 0]),
+                (2, [0, 1, 1, 0]),
+                (0, [1, 1, 1, 0]),
+                (3, [1, 1, 1, 1]),
+            ] {
+                assert_eq!(woken(&counts), expected);
+                let cx = &mut Context::from_waker(&wakers[i]);
+                let Poll::Ready(mut guard) = waiters[i].as_mut().poll(cx) else {
+                    panic!("waiter {i} was woken for the lock");
+                };
+                guard.push(i);
+            }
+            assert_eq!(*mutex.try_lock().unwrap(), [1, 2, 0, 3]);
+        }
+    };
+}
+
+mod highest_priority_is_woken_first {
+    use super::*;
+
+    highest_priority_is_woken_first!(sync);
+    highest_priority_is_woken_first!(unsync);
+}
+
+macro_rules! cancelled_priority_waiter_passes_wakeup_on {
+    ($sync:ident) => {
+        #[test]
+        fn $sync() {
+            use synchrony::$sync::mutex::Mutex;
+
+            let mutex = Mutex::new(());
+            let guard = mutex.try_lock().unwrap();
+            let (counts, wakers) = wakers::<3>();
+            let mut low = Box::pin(mutex.lock_with_priority(1));
+            let mut middle = Box::pin(mutex.lock_with_priority(2));
+            let mut high = Box::pin(mutex.lock_with_priority(3));
+            let waiters = [low.as_mut(), middle.as_mut(), high.as_mut()];
+            for (waiter, waker) in waiters.into_iter().zip(&wakers) {
+                assert!(waiter.poll(&mut Context::from_waker(waker)).is_pending());
+            }
+
+            drop(guard);
+            assert_eq!(woken(&counts), [0, 0, 1]);
+            // Woken, but dropped before it could take the lock.
+            drop(high);
+            assert_eq!(woken(&counts), [0, 1, 1]);
+            let cx = &mut Context::from_waker(&wakers[1]);
+            assert!(middle.as_mut().poll(cx).is_ready());
+            assert_eq!(woken(&counts), [1, 1, 1]);
+        }
+    };
+}
+
+mod cancelled_priority_waiter_passes_wakeup_on {
+    use super::*;
+
+    cancelled_priority_waiter_passes_wakeup_on!(sync);
+    cancelled_priority_waiter_passes_wakeup_on!(unsync);
+}
+
+macro_rules! mapped_unlocked_keeps_the_projection {
+    ($sync:ident) => {
+        #[test]
+        fn $sync() {
+            use synchrony::$sync::mutex::{MappedMutexGuard, Mutex, MutexGuard};
+
+            let mut cx = Context::from_waker(noop_waker_ref());
+            let mutex = Mutex::new((0, 0));
+            let mut second = MutexGuard::map(mutex.try_lock().unwrap(), |(_, second)| second);
+            {
+                let unlocked = pin!(MappedMutexGuard::unlocked(&mut second, || async {
+                    *mutex.try_lock().unwrap() = (1, 2);
+                }));
+                assert!(unlocked.poll(&mut cx).is_ready());
+            }
+            *second += 10;
+            assert!(mutex.try_lock().is_none());
+            drop(second);
+            assert_eq!(*mutex.try_lock().unwrap(), (1, 12));
+        }
+    };
+}
+
+mod mapped_unlocked_keeps_the_projection {
+    use super::*;
+
+    mapped_unlocked_keeps_the_projection!(sync);
+    mapped_unlocked_keeps_the_projection!(unsync);
+}
+
+macro_rules! cancelled_unlocked_leaves_the_guard_unusable {
+    ($sync:ident) => {
+        #[test]
+        #[should_panic = "the guard was used after `unlocked` was cancelled"]
+        fn $sync() {
+            use synchrony::$sync::mutex::{MappedMutexGuard, Mutex, MutexGuard};
+
+            let mut cx = Context::from_waker(noop_waker_ref());
+            let mutex = Mutex::new(0);
+            let mut guard = MutexGuard::map(mutex.try_lock().unwrap(), |value| value);
+            {
+                let mut unlocked = pin!(MappedMutexGuard::unlocked(
+                    &mut guard,
+                    std::future::pending::<()>
+                ));
+                assert!(unlocked.as_mut().poll(&mut cx).is_pending());
+            }
+            // The lock was left released.
+            assert!(mutex.try_lock().is_some());
+            *guard += 1;
+        }
+    };
+}
+
+mod cancelled_unlocked_leaves_the_guard_unusable {
+    use super::*;
+
+    cancelled_unlocked_leaves_the_guard_unusable!(sync);
+    cancelled_unlocked_leaves_the_guard_unusable!(unsync);
+}
@@ -0,0 +1,56 @@
+//! Run with `cargo test`.
+#![cfg(all(feature = "std", not(loom)))]
+
+use std::{
+    sync::{
+        Barrier,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use synchrony::sync::once::OnceLock;
+
+#[test]
+fn waiters_block_on_a_slow_initializer() {
+    let cell = OnceLock::new();
+    let runs = AtomicUsize::new(0);
+    let barrier = Barrier::new(4);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                barrier.wait();
+                let value = cell.get_or_init(|| {
+                    runs.fetch_add(1, Ordering::Relaxed);
+                    thread::sleep(Duration::from_millis(50));
+                    42
+                });
+                assert_eq!(*value, 42);
+            });
+        }
+    });
+    assert_eq!(runs.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn waiters_take_over_a_failed_initializer() {
+    let cell = OnceLock::new();
+    let started = Barrier::new(2);
+    thread::scope(|s| {
+        s.spawn(|| {
+            let result = cell.get_or_try_init(|| {
+                started.wait();
+                // Give the other thread time to wait for us.
+                thread::sleep(Duration::from_millis(50));
+                Err(())
+            });
+            assert_eq!(result, Err(()));
+        });
+        s.spawn(|| {
+            started.wait();
+            assert_eq!(cell.get_or_init(|| 1), &1);
+        });
+    });
+    assert_eq!(cell.get(), Some(&1));
+}
@@ -0,0 +1,200 @@
+//! Run with `cargo test --features rate_limit`.
+#![cfg(all(feature = "rate_limit", not(loom)))]
+
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::{Pin, pin},
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::task::{ArcWake, noop_waker_ref, waker};
+use synchrony::sync::rate_limit::{Clock, RateLimiter};
+
+// A fake clock, which only moves forward when told to.
+#[derive(Clone, Default)]
+struct FakeClock(Rc<Cell<Duration>>);
+
+impl FakeClock {
+    fn advance(&self, duration: Duration) {
+        self.0.set(self.0.get() + duration);
+    }
+}
+
+struct Sleep {
+    clock: FakeClock,
+    until: Duration,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    // Polled by hand, so there is no need to wake anything.
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        if self.clock.now() >= self.until {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Clock for FakeClock {
+    type Sleep = Sleep;
+
+    fn now(&self) -> Duration {
+        self.0.get()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Sleep {
+            clock: self.clone(),
+            until: self.now() + duration,
+        }
+    }
+}
+
+#[derive(Default)]
+struct CountWakes(AtomicUsize);
+
+impl ArcWake for CountWakes {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn poll(future: Pin<&mut impl Future<Output = ()>>) -> Poll<()> {
+    future.poll(&mut Context::from_waker(noop_waker_ref()))
+}
+
+// 10 tokens per second, with bursts of up to 5, and no token left.
+fn drained(clock: &FakeClock) -> RateLimiter<FakeClock> {
+    let limiter = RateLimiter::new(clock.clone(), 10, Duration::from_secs(1), 5);
+    assert!(limiter.try_acquire(5));
+    limiter
+}
+
+#[test]
+fn large_acquire_is_not_overtaken() {
+    let clock = FakeClock::default();
+    let limiter = drained(&clock);
+
+    let mut large = pin!(limiter.acquire(5));
+    let mut small = pin!(limiter.acquire(1));
+    assert!(poll(large.as_mut()).is_pending());
+    assert!(poll(small.as_mut()).is_pending());
+    assert_eq!(limiter.waiters(), 2);
+
+    // There is enough for the small request, but it is queued behind the
+    // large one.
+    clock.advance(Duration::from_millis(100));
+    assert!(poll(small.as_mut()).is_pending());
+    assert!(poll(large.as_mut()).is_pending());
+
+    clock.advance(Duration::from_millis(400));
+    assert!(poll(small.as_mut()).is_pending());
+    assert!(poll(large.as_mut()).is_ready());
+    assert_eq!(limiter.waiters(), 1);
+
+    // The large request took everything, so the small one waits for a refill.
+    assert!(poll(small.as_mut()).is_pending());
+    clock.advance(Duration::from_millis(100));
+    assert!(poll(small.as_mut()).is_ready());
+    assert_eq!(limiter.waiters(), 0);
+}
+
+#[test]
+fn cancelled_head_wakes_the_next_waiter() {
+    let clock = FakeClock::default();
+    let limiter = drained(&clock);
+
+    let mut head = Box::pin(limiter.acquire(1));
+    let mut next = pin!(limiter.acquire(1));
+    assert!(poll(head.as_mut()).is_pending());
+    let wakes = Arc::new(CountWakes::default());
+    let next_waker = waker(wakes.clone());
+    let mut cx = Context::from_waker(&next_waker);
+    assert!(next.as_mut().poll(&mut cx).is_pending());
+    assert_eq!(wakes.0.load(Ordering::Relaxed), 0);
+
+    // Dropping the head of the queue hands it over to the next waiter.
+    drop(head);
+    assert_eq!(wakes.0.load(Ordering::Relaxed), 1);
+    assert_eq!(limiter.waiters(), 1);
+
+    assert!(next.as_mut().poll(&mut cx).is_pending());
+    clock.advance(Duration::from_millis(100));
+    assert!(next.as_mut().poll(&mut cx).is_ready());
+    assert_eq!(limiter.waiters(), 0);
+}
+
+// A clock which never moves, so that the head of the queue sleeps forever.
+struct Frozen;
+
+impl Clock for Frozen {
+    type Sleep = std::future::Pending<()>;
+
+    fn now(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn sleep(&self, _: Duration) -> Self::Sleep {
+        std::future::pending()
+    }
+}
+
+thread_local! {
+    static LIMITER: RateLimiter<Frozen> =
+        const { RateLimiter::new(Frozen, 10, Duration::from_secs(1), 5) };
+}
+
+// A waker which looks into the limiter when woken or dropped.
+struct Reenter;
+
+impl ArcWake for Reenter {
+    fn wake_by_ref(_: &Arc<Self>) {
+        LIMITER.with(|limiter| limiter.waiters());
+    }
+}
+
+impl Drop for Reenter {
+    fn drop(&mut self) {
+        LIMITER.with(|limiter| limiter.waiters());
+    }
+}
+
+#[test]
+fn wakers_are_used_outside_the_bucket() {
+    LIMITER.with(|limiter| {
+        assert!(limiter.try_acquire(5));
+        let mut head = Box::pin(limiter.acquire(1));
+        let mut next = Box::pin(limiter.acquire(1));
+        assert!(poll(head.as_mut()).is_pending());
+        let next_waker = waker(Arc::new(Reenter));
+        assert!(
+            next.as_mut()
+                .poll(&mut Context::from_waker(&next_waker))
+                .is_pending()
+        );
+
+        // Replaced by another waker.
+        let next_waker = waker(Arc::new(Reenter));
+        assert!(
+            next.as_mut()
+                .poll(&mut Context::from_waker(&next_waker))
+                .is_pending()
+        );
+        drop(next_waker);
+
+        // Woken as the new head, then removed from the queue.
+        drop(head);
+        drop(next);
+        assert_eq!(limiter.waiters(), 0);
+    });
+}
@@ -0,0 +1,60 @@
+//! Run with `cargo test --features serde,mutex`.
+#![cfg(all(feature = "serde", feature = "mutex", not(loom)))]
+
+use std::sync::atomic::Ordering;
+
+fn round_trip<T: serde::Serialize + serde::de::DeserializeOwned>(value: &T, json: &str) -> T {
+    assert_eq!(serde_json::to_string(value).unwrap(), json);
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn unsync_atomic() {
+    use synchrony::unsync::atomic::{AtomicBool, AtomicU32};
+
+    let atomic = round_trip(&AtomicU32::new(42), "42");
+    assert_eq!(atomic.load(Ordering::Relaxed), 42);
+    let atomic = round_trip(&AtomicBool::new(true), "true");
+    assert!(atomic.load(Ordering::Relaxed));
+}
+
+macro_rules! flavor {
+    ($sync:ident) => {
+        mod $sync {
+            use super::*;
+
+            #[test]
+            fn flag() {
+                let flag = round_trip(&synchrony::$sync::flag::Flag::new(true), "true");
+                assert!(flag.get());
+            }
+
+            #[test]
+            fn blocking_mutex() {
+                let mutex = synchrony::$sync::mutex_blocking::Mutex::new(vec![1, 2]);
+                let mutex = round_trip(&mutex, "[1,2]");
+                assert_eq!(*mutex.lock(), [1, 2]);
+            }
+
+            #[test]
+            fn mutex() {
+                let mutex = synchrony::$sync::mutex::Mutex::new(vec![1, 2]);
+                let mutex = round_trip(&mutex, "[1,2]");
+                assert_eq!(*mutex.try_lock().unwrap(), [1, 2]);
+            }
+
+            #[test]
+            fn held_mutex() {
+                let mutex = synchrony::$sync::mutex::Mutex::new(0);
+                let guard = mutex.try_lock().unwrap();
+                let error = serde_json::to_string(&mutex).unwrap_err();
+                assert_eq!(error.to_string(), "mutex is locked");
+                drop(guard);
+                assert_eq!(serde_json::to_string(&mutex).unwrap(), "0");
+            }
+        }
+    };
+}
+
+flavor!(sync);
+flavor!(unsync);
@@ -0,0 +1,49 @@
+//! Run with `cargo test --features waker_slot`.
+#![cfg(all(feature = "waker_slot", not(loom)))]
+
+use std::sync::Arc;
+
+use futures::task::{ArcWake, noop_waker_ref, waker};
+
+macro_rules! wakers_are_dropped_outside_the_queue {
+    ($sync:ident) => {
+        #[test]
+        fn $sync() {
+            use synchrony::$sync::waker_slot::WakerSet;
+
+            thread_local! {
+                static SET: WakerSet = const { WakerSet::new() };
+            }
+
+            // A waker which looks into the set when dropped.
+            struct Reenter;
+
+            impl ArcWake for Reenter {
+                fn wake_by_ref(_: &Arc<Self>) {}
+            }
+
+            impl Drop for Reenter {
+                fn drop(&mut self) {
+                    SET.with(|set| set.len());
+                }
+            }
+
+            SET.with(|set| {
+                let key = set.insert(&waker(Arc::new(Reenter)));
+                // Replaced by another waker.
+                set.update(key, noop_waker_ref());
+                set.update(key, &waker(Arc::new(Reenter)));
+                // Removed from the set.
+                assert!(!set.remove(key));
+                assert!(set.is_empty());
+            });
+        }
+    };
+}
+
+mod wakers_are_dropped_outside_the_queue {
+    use super::*;
+
+    wakers_are_dropped_outside_the_queue!(sync);
+    wakers_are_dropped_outside_the_queue!(unsync);
+}